@@ -0,0 +1,24 @@
+//! Records build environment details shown in the optional system info
+//! section of panic reports.
+
+use std::env;
+use std::process::Command;
+
+fn main() {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    let version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|x| String::from_utf8(x.stdout).ok())
+        .map(|x| x.trim().to_owned())
+        .unwrap_or_else(|| "unknown".to_owned());
+
+    println!("cargo:rustc-env=COLORBT_RUSTC_VERSION={}", version);
+    println!(
+        "cargo:rustc-env=COLORBT_TARGET={}",
+        env::var("TARGET").unwrap_or_default()
+    );
+    println!("cargo:rerun-if-env-changed=RUSTC");
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,129 @@
+//! Attribute macros for `color-backtrace`, re-exported there with the
+//! `macros` feature. See the documentation over there.
+
+use proc_macro::{Delimiter, Group, Ident, TokenStream, TokenTree};
+
+/// Wrap `main`, installing the panic handler first and printing errors
+/// returned from it.
+#[proc_macro_attribute]
+pub fn main(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut install = parse("let __color_backtrace_printer: ::color_backtrace::BacktracePrinter =");
+    install.extend(printer_expr(args));
+    install.extend(parse(
+        "; __color_backtrace_printer.clone().install(::color_backtrace::default_output_stream());",
+    ));
+
+    wrap_fn(item, false, |inner| {
+        let mut args = parse("&__color_backtrace_printer,");
+        args.extend(inner);
+
+        let mut body = install;
+        body.extend(parse("::color_backtrace::__private::report_main_result"));
+        body.extend([TokenTree::Group(Group::new(Delimiter::Parenthesis, args))]);
+        body
+    })
+}
+
+/// Turn the function into a test, making sure the panic handler is installed
+/// first.
+#[proc_macro_attribute]
+pub fn test(args: TokenStream, item: TokenStream) -> TokenStream {
+    if !args.is_empty() {
+        return compile_error("`#[color_backtrace::test]` takes no arguments");
+    }
+
+    let mut out = parse("#[::core::prelude::v1::test]");
+    out.extend(wrap_fn(item, true, |inner| {
+        let mut body = parse("::color_backtrace::install_once();");
+        body.extend(inner);
+        body
+    }));
+    out
+}
+
+/// The printer expression given as attribute arguments, if any.
+fn printer_expr(args: TokenStream) -> TokenStream {
+    if args.is_empty() {
+        return parse("::color_backtrace::BacktracePrinter::default()");
+    }
+    TokenStream::from(TokenTree::Group(Group::new(Delimiter::Parenthesis, args)))
+}
+
+/// Rewrite `fn name(..) .. { .. }` into a function of the same name without
+/// arguments, which defines the original function under a different name and
+/// runs the tokens produced by `body` given a call of it. The return type is
+/// kept if requested, the body then has to evaluate to it.
+///
+/// For `async fn`s, both functions stay `async` and the call is awaited, so
+/// they keep working below and above the attribute of an async runtime.
+fn wrap_fn(
+    item: TokenStream,
+    keep_return_type: bool,
+    body: impl FnOnce(TokenStream) -> TokenStream,
+) -> TokenStream {
+    let tokens: Vec<_> = item.into_iter().collect();
+    let pos = tokens.windows(2).position(|x| {
+        matches!(&x[0], TokenTree::Ident(i) if i.to_string() == "fn")
+            && matches!(&x[1], TokenTree::Ident(_))
+    });
+    let pos = match pos {
+        Some(pos) => pos,
+        None => return compile_error("expected a function"),
+    };
+    let span = tokens[pos + 1].span();
+    let inner = Ident::new("__color_backtrace_inner", span);
+    let is_async = tokens[..pos]
+        .iter()
+        .any(|x| matches!(x, TokenTree::Ident(i) if i.to_string() == "async"));
+
+    // Attributes and qualifiers stay on the outer function.
+    let mut out: TokenStream = tokens[..pos].iter().cloned().collect();
+    out.extend([
+        TokenTree::Ident(Ident::new("fn", span)),
+        TokenTree::Ident(Ident::new(&tokens[pos + 1].to_string(), span)),
+        TokenTree::Group(Group::new(Delimiter::Parenthesis, TokenStream::new())),
+    ]);
+    if keep_return_type {
+        // Everything between the arguments and the body.
+        let end = tokens.len().saturating_sub(1).max(pos + 3);
+        out.extend(tokens[pos + 3..end].iter().cloned());
+    }
+
+    let mut inner_fn = TokenStream::new();
+    if is_async {
+        inner_fn.extend([TokenTree::Ident(Ident::new("async", span))]);
+    }
+    inner_fn.extend([
+        TokenTree::Ident(Ident::new("fn", span)),
+        TokenTree::Ident(inner.clone()),
+    ]);
+    inner_fn.extend(tokens[pos + 2..].iter().cloned());
+
+    let mut invocation = call(TokenStream::from(TokenTree::Ident(inner)));
+    if is_async {
+        invocation.extend(parse(".await"));
+    }
+    inner_fn.extend(body(invocation));
+
+    out.extend([TokenTree::Group(Group::new(Delimiter::Brace, inner_fn))]);
+    out
+}
+
+fn parse(code: &str) -> TokenStream {
+    code.parse().unwrap()
+}
+
+/// `f()`
+fn call(mut f: TokenStream) -> TokenStream {
+    f.extend([TokenTree::Group(Group::new(
+        Delimiter::Parenthesis,
+        TokenStream::new(),
+    ))]);
+    f
+}
+
+fn compile_error(msg: &str) -> TokenStream {
+    format!("::core::compile_error!({:?});", msg)
+        .parse()
+        .unwrap()
+}
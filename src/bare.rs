@@ -0,0 +1,193 @@
+//! Panic formatting for environments without `std`.
+//!
+//! Nothing in here allocates or requires `std`: frames are supplied by the
+//! caller (e.g. from a custom unwinder or a list of return addresses) and
+//! everything is rendered to a [`core::fmt::Write`] sink, such as an RTT
+//! channel or a serial port wrapper.
+//!
+//! ```rust
+//! use color_backtrace::bare::{BareFormatter, BareFrame};
+//!
+//! let frames = [BareFrame {
+//!     name: Some("firmware::main"),
+//!     file: Some("src/main.rs"),
+//!     line: Some(42),
+//!     ip: 0x0800_1234,
+//! }];
+//!
+//! let mut out = String::new();
+//! BareFormatter::new()
+//!     .write_panic(&mut out, &"oh no", None, &frames)
+//!     .unwrap();
+//! assert!(out.contains("firmware::main"));
+//! ```
+
+use core::fmt::{self, Display, Write};
+use core::panic::Location;
+
+const RED: &str = "\x1b[31m";
+const BRIGHT_RED: &str = "\x1b[91m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const MAGENTA: &str = "\x1b[35m";
+const RESET: &str = "\x1b[0m";
+
+/// A stack frame supplied by the user.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BareFrame<'a> {
+    pub name: Option<&'a str>,
+    pub file: Option<&'a str>,
+    pub line: Option<u32>,
+    pub ip: usize,
+}
+
+impl BareFrame<'_> {
+    /// Heuristically determine whether the frame is part of the language
+    /// runtime or core libraries.
+    pub fn is_dependency_code(&self) -> bool {
+        const SYM_PREFIXES: &[&str] =
+            &["core::", "alloc::", "std::", "__rust_", "rust_begin_unwind"];
+
+        match self.name {
+            Some(name) => SYM_PREFIXES.iter().any(|x| name.starts_with(x)),
+            None => false,
+        }
+    }
+}
+
+/// Formatter rendering panics to a [`core::fmt::Write`] sink.
+#[derive(Debug, Clone, Copy)]
+pub struct BareFormatter<'a> {
+    message: &'a str,
+    color: bool,
+    print_addresses: bool,
+    compact: bool,
+}
+
+impl Default for BareFormatter<'_> {
+    fn default() -> Self {
+        Self {
+            message: "The application panicked (crashed).",
+            color: false,
+            print_addresses: true,
+            compact: false,
+        }
+    }
+}
+
+impl<'a> BareFormatter<'a> {
+    /// Alias for `BareFormatter::default`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Controls the "greeting" message of the panic.
+    ///
+    /// Defaults to `"The application panicked (crashed)"`.
+    pub fn message(mut self, message: &'a str) -> Self {
+        self.message = message;
+        self
+    }
+
+    /// Controls whether ANSI color codes are emitted.
+    ///
+    /// Defaults to `false`.
+    pub fn color(mut self, val: bool) -> Self {
+        self.color = val;
+        self
+    }
+
+    /// Controls whether frame addresses are printed.
+    ///
+    /// Defaults to `true`, since the frames are often not symbolicated.
+    pub fn print_addresses(mut self, val: bool) -> Self {
+        self.print_addresses = val;
+        self
+    }
+
+    /// Controls whether each frame is printed on a single line, with the
+    /// location following the function name.
+    ///
+    /// Defaults to `false`.
+    pub fn compact(mut self, val: bool) -> Self {
+        self.compact = val;
+        self
+    }
+
+    fn paint(&self, out: &mut impl Write, color: &str) -> fmt::Result {
+        if self.color {
+            out.write_str(color)?;
+        }
+        Ok(())
+    }
+
+    /// Render a panic message, its location and a list of frames.
+    ///
+    /// In a `#[panic_handler]`, the message is `info.message()` and the
+    /// location `info.location()`.
+    pub fn write_panic(
+        &self,
+        out: &mut impl Write,
+        message: &dyn Display,
+        location: Option<&Location<'_>>,
+        frames: &[BareFrame<'_>],
+    ) -> fmt::Result {
+        self.paint(out, RED)?;
+        writeln!(out, "{}", self.message)?;
+        self.paint(out, RESET)?;
+
+        write!(out, "Message:  ")?;
+        self.paint(out, CYAN)?;
+        writeln!(out, "{}", message)?;
+        self.paint(out, RESET)?;
+
+        write!(out, "Location: ")?;
+        if let Some(loc) = location {
+            self.paint(out, MAGENTA)?;
+            writeln!(out, "{}:{}", loc.file(), loc.line())?;
+            self.paint(out, RESET)?;
+        } else {
+            writeln!(out, "<unknown>")?;
+        }
+
+        if !frames.is_empty() {
+            writeln!(out)?;
+            self.write_frames(out, frames)?;
+        }
+
+        Ok(())
+    }
+
+    /// Render a list of frames.
+    pub fn write_frames(&self, out: &mut impl Write, frames: &[BareFrame<'_>]) -> fmt::Result {
+        writeln!(out, "{:━^80}", " BACKTRACE ")?;
+
+        for (frame, i) in frames.iter().zip(1..) {
+            write!(out, "{:>2}: ", i)?;
+            if self.print_addresses {
+                write!(out, "0x{:016x} - ", frame.ip)?;
+            }
+
+            self.paint(
+                out,
+                if frame.is_dependency_code() {
+                    GREEN
+                } else {
+                    BRIGHT_RED
+                },
+            )?;
+            write!(out, "{}", frame.name.unwrap_or("<unknown>"))?;
+            self.paint(out, RESET)?;
+
+            let separator = if self.compact { " " } else { "\n    " };
+            match (frame.file, frame.line) {
+                (Some(file), Some(line)) => writeln!(out, "{}at {}:{}", separator, file, line)?,
+                (Some(file), None) => writeln!(out, "{}at {}:<unknown line>", separator, file)?,
+                _ if self.compact => writeln!(out)?,
+                _ => writeln!(out, "{}at <unknown source file>", separator)?,
+            }
+        }
+
+        Ok(())
+    }
+}
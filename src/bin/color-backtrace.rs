@@ -0,0 +1,85 @@
+//! Colorize panics and backtraces in text output, see `color_backtrace::text`.
+
+use color_backtrace::{text, BacktracePrinter, Verbosity};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::process::ExitCode;
+use termcolor::ColorChoice;
+
+const USAGE: &str = "\
+Usage: color-backtrace [OPTIONS] [FILE]...
+
+Re-renders Rust panics and backtraces found in the given files, or in the
+standard input if none are given, with colors, frame filtering and source
+snippets. All other lines are passed through unchanged.
+
+Options:
+      --full           Print source snippets for all frames
+      --color <WHEN>   Colorize the output: auto, always or never [default: auto]
+  -h, --help           Print this help
+  -V, --version        Print the version";
+
+fn main() -> ExitCode {
+    let mut verbosity = Verbosity::Medium;
+    let mut color = None;
+    let mut files = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--full" => verbosity = Verbosity::Full,
+            "--color" => color = args.next(),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            "-V" | "--version" => {
+                println!("color-backtrace {}", env!("CARGO_PKG_VERSION"));
+                return ExitCode::SUCCESS;
+            }
+            x if x.starts_with("--color=") => color = Some(x["--color=".len()..].to_owned()),
+            x if x.starts_with('-') && x != "-" => {
+                return usage_error(&format!("unknown option `{}`", x))
+            }
+            _ => files.push(arg),
+        }
+    }
+
+    let choice = match color.as_deref() {
+        None | Some("auto") => ColorChoice::Auto,
+        Some("never") => ColorChoice::Never,
+        Some("always") => ColorChoice::Always,
+        Some(x) => return usage_error(&format!("invalid value `{}` for `--color`", x)),
+    };
+
+    let printer = BacktracePrinter::new().lib_verbosity(verbosity);
+    let mut out = color_backtrace::stdout_output_stream(choice);
+    if files.is_empty() {
+        files.push("-".to_owned());
+    }
+
+    for file in files {
+        let result = match file.as_str() {
+            "-" => text::rewrite(&printer, io::stdin().lock(), &mut out),
+            path => {
+                File::open(path).and_then(|x| text::rewrite(&printer, BufReader::new(x), &mut out))
+            }
+        };
+        match result {
+            Ok(()) => {}
+            // E.g. piped into `head`.
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => break,
+            Err(e) => {
+                eprintln!("color-backtrace: {}: {}", file, e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("color-backtrace: {}\n\n{}", message, USAGE);
+    ExitCode::from(2)
+}
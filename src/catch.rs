@@ -0,0 +1,205 @@
+//! Catching panics with their context, see [`catch`].
+
+use crate::printer::DeferredFrames;
+use crate::{
+    BacktracePrinter, Frame, PanicHeader, PanicInfo, PanicLocation, Renderer, TermRenderer,
+};
+use std::any::Any;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::io;
+use std::panic::UnwindSafe;
+use std::sync::Once;
+use termcolor::WriteColor;
+
+thread_local! {
+    /// Number of nested `catch` calls running on this thread.
+    static CATCHING: Cell<usize> = const { Cell::new(0) };
+
+    /// Context of the last panic caught on this thread.
+    static CAUGHT: RefCell<Option<Context>> = const { RefCell::new(None) };
+}
+
+static DISPATCH_HOOK: Once = Once::new();
+
+/// What the hook records while the stack is still intact.
+struct Context {
+    location: Option<PanicLocation>,
+    thread: Option<String>,
+    frames: DeferredFrames,
+}
+
+/// Run `f`, catching a panic along with its location and backtrace.
+///
+/// In contrast to `std::panic::catch_unwind`, the context of the panic is
+/// kept and can be rendered with any printer later on, see
+/// [`CaughtPanic::print`]. Panics caught this way aren't printed by the panic
+/// hook, while all others are passed on to it as usual. This makes it a good
+/// fit for plugin hosts and test runners, which need to display panics
+/// without taking over the global panic handling.
+///
+/// On first use, the currently installed panic hook is wrapped to record the
+/// context. Hooks installed later replace the wrapper, in which case the
+/// location and frames of caught panics are no longer available.
+///
+/// The hook can't tell who is going to catch a panic, so it stays silent for
+/// every panic on this thread while `f` runs. This includes panics that `f`
+/// catches itself, e.g. with `std::panic::catch_unwind`, which aren't passed
+/// on to the previous hook either.
+///
+/// ```rust
+/// use color_backtrace::BacktracePrinter;
+///
+/// let caught = color_backtrace::catch(|| panic!("plugin crashed")).unwrap_err();
+/// assert_eq!(caught.message(), "plugin crashed");
+/// assert!(caught.location().is_some());
+///
+/// let mut out = color_backtrace::FmtStream::new(String::new());
+/// caught.print(&BacktracePrinter::new(), &mut out).unwrap();
+/// assert!(out.into_inner().contains("plugin crashed"));
+/// ```
+pub fn catch<T>(f: impl FnOnce() -> T + UnwindSafe) -> Result<T, CaughtPanic> {
+    DISPATCH_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |pi| match CATCHING.with(Cell::get) {
+            0 => previous(pi),
+            _ => record_context(pi),
+        }));
+    });
+
+    // Don't attribute the context of an earlier panic to this one, e.g. when
+    // `f` resumes unwinding with a payload of its own, which skips the hook.
+    CAUGHT.with(|x| x.borrow_mut().take());
+    CATCHING.with(|x| x.set(x.get() + 1));
+    let result = std::panic::catch_unwind(f);
+    CATCHING.with(|x| x.set(x.get() - 1));
+    let context = CAUGHT.with(|x| x.borrow_mut().take());
+
+    result.map_err(|payload| {
+        let (location, thread, frames) = match context {
+            Some(x) => (x.location, x.thread, x.frames),
+            None => (None, None, DeferredFrames::resolved(Vec::new())),
+        };
+        CaughtPanic {
+            payload,
+            location,
+            thread,
+            frames: Box::new(frames),
+        }
+    })
+}
+
+fn record_context(pi: &PanicInfo<'_>) {
+    // Only symbolicated when the frames are accessed, keeping panics that are
+    // caught and dropped cheap.
+    let frames = BacktracePrinter::default()
+        .capture_deferred()
+        .map(|mut frames| {
+            // Drop the capturing machinery, up to and including this function.
+            let ours = frames.iter().position(|x| {
+                x.name
+                    .as_deref()
+                    .is_some_and(|name| name.starts_with("color_backtrace::catch::record_context"))
+            });
+            if let Some(ours) = ours {
+                frames.drain(..=ours);
+            }
+            for (frame, n) in frames.iter_mut().zip(1..) {
+                frame.n = n;
+            }
+            frames
+        });
+
+    let context = Context {
+        location: pi.location().map(|x| PanicLocation {
+            file: x.file().to_owned(),
+            line: x.line(),
+            column: x.column(),
+        }),
+        thread: std::thread::current().name().map(ToOwned::to_owned),
+        frames,
+    };
+    CAUGHT.with(|x| *x.borrow_mut() = Some(context));
+}
+
+/// A panic caught by [`catch`].
+pub struct CaughtPanic {
+    payload: Box<dyn Any + Send>,
+    location: Option<PanicLocation>,
+    thread: Option<String>,
+    frames: Box<DeferredFrames>,
+}
+
+impl CaughtPanic {
+    /// The panic payload, as passed to `panic!` or `std::panic::panic_any`.
+    pub fn payload(&self) -> &(dyn Any + Send) {
+        &*self.payload
+    }
+
+    /// The panic message, or a placeholder for payloads other than strings.
+    pub fn message(&self) -> Cow<'_, str> {
+        crate::printer::payload_as_str(&*self.payload)
+    }
+
+    /// Where the panic occurred, if known.
+    pub fn location(&self) -> Option<&PanicLocation> {
+        self.location.as_ref()
+    }
+
+    /// Name of the panicking thread, if it has one.
+    pub fn thread(&self) -> Option<&str> {
+        self.thread.as_deref()
+    }
+
+    /// The frames captured when the panic occurred, innermost first.
+    ///
+    /// The frames are symbolicated on first access.
+    pub fn frames(&self) -> &[Frame] {
+        self.frames.get()
+    }
+
+    /// Render the panic with the given printer, like the panic handler would.
+    ///
+    /// The printer isn't installed as panic handler, so its library verbosity
+    /// applies, see [`BacktracePrinter::lib_verbosity`].
+    pub fn print(&self, printer: &BacktracePrinter, out: &mut impl WriteColor) -> io::Result<()> {
+        TermRenderer::new(printer, &mut *out).render_header(&PanicHeader {
+            title: &printer.message,
+            notes: self
+                .thread
+                .iter()
+                .map(|x| format!("thread '{}'", x))
+                .collect(),
+            message: printer.format_payload(&*self.payload),
+            location: self.location.clone(),
+        })?;
+        if printer.current_verbosity() >= crate::Verbosity::Medium && !self.frames().is_empty() {
+            writeln!(out)?;
+            printer.print_banner(out, " BACKTRACE ")?;
+            printer.print_frames(self.frames(), out)?;
+        }
+        Ok(())
+    }
+
+    /// Continue unwinding with the original payload.
+    pub fn resume_unwind(self) -> ! {
+        std::panic::resume_unwind(self.payload)
+    }
+
+    /// Unwrap the panic payload.
+    pub fn into_payload(self) -> Box<dyn Any + Send> {
+        self.payload
+    }
+}
+
+impl fmt::Debug for CaughtPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CaughtPanic")
+            .field("message", &self.message())
+            .field("location", &self.location)
+            .field("thread", &self.thread)
+            .field("frames", &self.frames)
+            .finish()
+    }
+}
@@ -0,0 +1,621 @@
+//! Loading printer settings from a configuration file.
+//!
+//! Lets the settings of deployed binaries be tuned without recompiling. The
+//! file is looked up at the path given by the `COLORBT_CONFIG` environment
+//! variable, else as `color-backtrace.toml` next to the executable.
+//!
+//! ```toml
+//! verbosity = "full"              # off, minimal, medium or full
+//! lib_verbosity = "medium"
+//! message = "The service crashed. Please report this to ops."
+//! theme = "adaptive"              # classic, truecolor or adaptive
+//! colors = "crate=red,bold;dep=green"
+//! hide = ["tokio::*", "*::poll"]  # symbol name patterns
+//! directives = "myapp::db=full"
+//! print_addresses = false
+//! strip_function_hash = true
+//! source_roots = ["/srv/myapp/src"]
+//!
+//! [paths]
+//! "/build/agent/myapp" = "/srv/myapp"
+//!
+//! [output]
+//! stderr = true
+//! file = "/var/log/myapp/panics.log"
+//! ```
+//!
+//! All keys are optional. The file format is a subset of TOML: tables,
+//! strings, booleans and arrays.
+//!
+//! ```rust,no_run
+//! color_backtrace::config::install();
+//! ```
+
+use crate::{
+    default_output_stream, file_output, BacktracePrinter, ColorScheme, Directives, TeeStream,
+    Verbosity,
+};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the configuration file looked up next to the executable.
+pub const FILE_NAME: &str = "color-backtrace.toml";
+
+/// Error returned when loading a configuration file fails.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file couldn't be read.
+    Io(io::Error),
+    /// The file is malformed or contains an invalid setting.
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "failed to read config: {}", e),
+            ConfigError::Parse { line, message } => {
+                write!(f, "invalid config, line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(e) => Some(e),
+            ConfigError::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+/// Printer settings read from a configuration file.
+///
+/// Each field corresponds to the key of the same name, see the
+/// [module docs](self) for an example. Unset keys are `None` or empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Config {
+    /// `verbosity`, see [`BacktracePrinter::verbosity`].
+    pub verbosity: Option<Verbosity>,
+    /// `lib_verbosity`, see [`BacktracePrinter::lib_verbosity`].
+    pub lib_verbosity: Option<Verbosity>,
+    /// `message`, see [`BacktracePrinter::message`].
+    pub message: Option<String>,
+    /// `theme`, the color scheme to start from: `classic`, `truecolor` or
+    /// `adaptive`.
+    pub theme: Option<String>,
+    /// `colors`, overrides applied on top of the theme, in the format of
+    /// [`ColorScheme::with_spec`].
+    pub colors: Option<String>,
+    /// `hide`, patterns of symbol names to hide, `*` matching anything.
+    pub hide: Vec<String>,
+    /// `directives`, in the format of [`Directives::parse`].
+    pub directives: Option<String>,
+    /// `print_addresses`, see [`BacktracePrinter::print_addresses`].
+    pub print_addresses: Option<bool>,
+    /// `strip_function_hash`, see [`BacktracePrinter::strip_function_hash`].
+    pub strip_function_hash: Option<bool>,
+    /// `source_roots`, see [`BacktracePrinter::add_source_root`].
+    pub source_roots: Vec<PathBuf>,
+    /// The entries of the `[paths]` table, see [`BacktracePrinter::map_path`].
+    pub path_maps: Vec<(PathBuf, PathBuf)>,
+    /// `output.stderr`, whether to print to stderr. Unset means `true`.
+    pub output_stderr: Option<bool>,
+    /// `output.file`, a file to append reports to.
+    pub output_file: Option<PathBuf>,
+}
+
+impl Config {
+    /// Location of the configuration file, if one exists: the path in
+    /// `COLORBT_CONFIG`, else [`FILE_NAME`] next to the executable.
+    pub fn find() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("COLORBT_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        let path = std::env::current_exe().ok()?.parent()?.join(FILE_NAME);
+        path.is_file().then_some(path)
+    }
+
+    /// Load the configuration file found by [`find`](Self::find), if any.
+    pub fn load_default() -> Result<Option<Self>, ConfigError> {
+        Self::find().map(Self::load).transpose()
+    }
+
+    /// Load the configuration file at the given path.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parse the contents of a configuration file.
+    ///
+    /// ```rust
+    /// use color_backtrace::{config::Config, Verbosity};
+    ///
+    /// let config = Config::parse("verbosity = \"full\"\nhide = [\"tokio::*\"]").unwrap();
+    /// assert_eq!(config.verbosity, Some(Verbosity::Full));
+    /// assert_eq!(config.hide, ["tokio::*"]);
+    /// ```
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let mut config = Config::default();
+        for (line, table, key, value) in parse_entries(text)? {
+            let err = |message: String| ConfigError::Parse { line, message };
+            let expected = |what: &str| err(format!("`{}` must be {}", key, what));
+            match (table.as_str(), key.as_str()) {
+                ("", "verbosity" | "lib_verbosity") => {
+                    let level = match value.as_str().map(str::to_ascii_lowercase).as_deref() {
+                        Some("off") => Verbosity::Off,
+                        Some("minimal") => Verbosity::Minimal,
+                        Some("medium") => Verbosity::Medium,
+                        Some("full") => Verbosity::Full,
+                        _ => return Err(expected("one of off, minimal, medium or full")),
+                    };
+                    match key.as_str() {
+                        "verbosity" => config.verbosity = Some(level),
+                        _ => config.lib_verbosity = Some(level),
+                    }
+                }
+                ("", "message") => {
+                    config.message = Some(value.into_string().ok_or_else(|| expected("a string"))?)
+                }
+                ("", "theme") => {
+                    let theme = value.into_string().ok_or_else(|| expected("a string"))?;
+                    if !matches!(theme.as_str(), "classic" | "truecolor" | "adaptive") {
+                        return Err(expected("one of classic, truecolor or adaptive"));
+                    }
+                    config.theme = Some(theme);
+                }
+                ("", "colors") => {
+                    let spec = value.into_string().ok_or_else(|| expected("a string"))?;
+                    ColorScheme::parse(&spec).map_err(|e| err(e.to_string()))?;
+                    config.colors = Some(spec);
+                }
+                ("", "hide") => {
+                    config.hide = value
+                        .into_strings()
+                        .ok_or_else(|| expected("a list of strings"))?
+                }
+                ("", "directives") => {
+                    let spec = value.into_string().ok_or_else(|| expected("a string"))?;
+                    Directives::parse(&spec).map_err(|e| err(e.to_string()))?;
+                    config.directives = Some(spec);
+                }
+                ("", "print_addresses") => {
+                    config.print_addresses =
+                        Some(value.as_bool().ok_or_else(|| expected("a boolean"))?)
+                }
+                ("", "strip_function_hash") => {
+                    config.strip_function_hash =
+                        Some(value.as_bool().ok_or_else(|| expected("a boolean"))?)
+                }
+                ("", "source_roots") => {
+                    let roots = value
+                        .into_strings()
+                        .ok_or_else(|| expected("a list of strings"))?;
+                    config.source_roots = roots.into_iter().map(PathBuf::from).collect();
+                }
+                ("paths", from) => {
+                    let to = value.into_string().ok_or_else(|| expected("a string"))?;
+                    config.path_maps.push((from.into(), to.into()));
+                }
+                ("output", "stderr") => {
+                    config.output_stderr =
+                        Some(value.as_bool().ok_or_else(|| expected("a boolean"))?)
+                }
+                ("output", "file") => {
+                    let path = value.into_string().ok_or_else(|| expected("a string"))?;
+                    config.output_file = Some(path.into());
+                }
+                ("", _) => return Err(err(format!("unknown setting `{}`", key))),
+                _ => return Err(err(format!("unknown setting `{}.{}`", table, key))),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Apply the settings to the given printer.
+    pub fn apply(&self, mut printer: BacktracePrinter) -> BacktracePrinter {
+        if let Some(verbosity) = self.verbosity {
+            printer = printer.verbosity(verbosity);
+        }
+        if let Some(verbosity) = self.lib_verbosity {
+            printer = printer.lib_verbosity(verbosity);
+        }
+        if let Some(message) = &self.message {
+            printer = printer.message(message.clone());
+        }
+        if self.theme.is_some() || self.colors.is_some() {
+            let scheme = match self.theme.as_deref() {
+                Some("truecolor") => ColorScheme::truecolor(),
+                Some("adaptive") => ColorScheme::adaptive(),
+                _ => ColorScheme::classic(),
+            };
+            let scheme = match &self.colors {
+                Some(spec) => scheme.clone().with_spec(spec).unwrap_or(scheme),
+                None => scheme,
+            };
+            printer = printer.color_scheme(scheme);
+        }
+        if !self.hide.is_empty() {
+            let patterns = self.hide.clone();
+            printer = printer.add_frame_filter(Box::new(move |frames| {
+                frames.retain(|frame| {
+                    let name = match &frame.name {
+                        Some(name) => name,
+                        None => return true,
+                    };
+                    let (name, _) = crate::printer::split_hash_suffix(name);
+                    !patterns
+                        .iter()
+                        .any(|x| crate::printer::glob_matches(x, name))
+                })
+            }));
+        }
+        if let Some(directives) = self.directives.as_deref().and_then(|x| x.parse().ok()) {
+            printer = printer.directives(directives);
+        }
+        if let Some(val) = self.print_addresses {
+            printer = printer.print_addresses(val);
+        }
+        if let Some(val) = self.strip_function_hash {
+            printer = printer.strip_function_hash(val);
+        }
+        for root in &self.source_roots {
+            printer = printer.add_source_root(root.clone());
+        }
+        for (from, to) in &self.path_maps {
+            printer = printer.map_path(from.clone(), to.clone());
+        }
+        printer
+    }
+
+    /// Install the given printer with these settings applied, printing to
+    /// the configured outputs.
+    ///
+    /// Falls back to stderr only if the output file can't be opened.
+    pub fn install(&self, printer: BacktracePrinter) {
+        let printer = self.apply(printer);
+        let file = self.output_file.as_ref().and_then(|path| {
+            file_output(path)
+                .map_err(|e| eprintln!("color-backtrace: can't open {}: {}", path.display(), e))
+                .ok()
+        });
+        match (self.output_stderr.unwrap_or(true), file) {
+            (true, Some(file)) => printer.install(TeeStream::new(default_output_stream(), file)),
+            (false, Some(file)) => printer.install(file),
+            (_, None) => printer.install(default_output_stream()),
+        }
+    }
+}
+
+/// Install the default handler, with the settings from the configuration
+/// file applied if one is found.
+///
+/// Problems with the file are reported on stderr, falling back to the
+/// default settings.
+pub fn install() {
+    match Config::load_default() {
+        Ok(config) => config.unwrap_or_default().install(BacktracePrinter::new()),
+        Err(e) => {
+            eprintln!("color-backtrace: {}", e);
+            crate::install();
+        }
+    }
+}
+
+// ============================================================================================== //
+// [Parser]                                                                                       //
+// ============================================================================================== //
+
+#[derive(Debug)]
+enum Value {
+    Str(String),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    fn into_string(self) -> Option<String> {
+        match self {
+            Value::Str(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn into_strings(self) -> Option<Vec<String>> {
+        match self {
+            Value::Array(items) => items.into_iter().map(Value::into_string).collect(),
+            _ => None,
+        }
+    }
+}
+
+/// Split the file into `(line, table, key, value)` entries.
+fn parse_entries(text: &str) -> Result<Vec<(usize, String, String, Value)>, ConfigError> {
+    let mut entries = Vec::new();
+    let mut table = String::new();
+    let mut lines = text.lines().zip(1..);
+    while let Some((raw, line)) = lines.next() {
+        let err = |message: &str| ConfigError::Parse {
+            line,
+            message: message.to_owned(),
+        };
+
+        let content = strip_comment(raw).trim();
+        if content.is_empty() {
+            continue;
+        }
+        if let Some(name) = content.strip_prefix('[') {
+            let name = name
+                .strip_suffix(']')
+                .ok_or_else(|| err("unclosed table header"))?;
+            table = name.trim().to_owned();
+            continue;
+        }
+
+        let (key, rest) = parse_key(content).ok_or_else(|| err("expected `key = value`"))?;
+
+        // Arrays may span multiple lines.
+        let mut value_text = rest.to_owned();
+        while value_text.starts_with('[') && !value_text.ends_with(']') {
+            let (next, _) = lines.next().ok_or_else(|| err("unclosed array"))?;
+            value_text.push(' ');
+            value_text.push_str(strip_comment(next).trim());
+        }
+
+        let mut input = value_text.as_str();
+        let value = parse_value(&mut input).ok_or_else(|| err("invalid value"))?;
+        if !input.trim().is_empty() {
+            return Err(err("trailing characters after value"));
+        }
+        entries.push((line, table.clone(), key, value));
+    }
+    Ok(entries)
+}
+
+/// Remove a trailing `#` comment, unless the `#` is part of a string.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+/// Parse a bare or quoted key followed by `=`, returning the key and the
+/// remaining input.
+fn parse_key(input: &str) -> Option<(String, &str)> {
+    let (key, rest) = if input.starts_with(['"', '\'']) {
+        let mut rest = input;
+        let key = parse_string(&mut rest)?;
+        (key, rest)
+    } else {
+        let end = input.find('=')?;
+        let key = input[..end].trim();
+        let valid = !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            return None;
+        }
+        (key.to_owned(), &input[end..])
+    };
+    Some((key, rest.trim_start().strip_prefix('=')?.trim()))
+}
+
+fn parse_value(input: &mut &str) -> Option<Value> {
+    *input = input.trim_start();
+    if input.starts_with(['"', '\'']) {
+        return parse_string(input).map(Value::Str);
+    }
+    if let Some(rest) = input.strip_prefix('[') {
+        *input = rest;
+        let mut items = Vec::new();
+        loop {
+            *input = input.trim_start();
+            if let Some(rest) = input.strip_prefix(']') {
+                *input = rest;
+                return Some(Value::Array(items));
+            }
+            items.push(parse_value(input)?);
+            *input = input.trim_start();
+            if let Some(rest) = input.strip_prefix(',') {
+                *input = rest;
+            } else if !input.starts_with(']') {
+                return None;
+            }
+        }
+    }
+
+    let end = input
+        .find(|c: char| c.is_whitespace() || c == ',' || c == ']')
+        .unwrap_or(input.len());
+    let (word, rest) = input.split_at(end);
+    let value = match word {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => return None,
+    };
+    *input = rest;
+    Some(value)
+}
+
+/// Parse a basic (`"..."`, with escapes) or literal (`'...'`) string.
+fn parse_string(input: &mut &str) -> Option<String> {
+    let quote = input.chars().next()?;
+    let mut out = String::new();
+    let mut chars = input[1..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c == quote => {
+                *input = &input[1 + i + 1..];
+                return Some(out);
+            }
+            '\\' if quote == '"' => match chars.next()?.1 {
+                'n' => out.push('\n'),
+                't' => out.push('\t'),
+                '\\' => out.push('\\'),
+                '"' => out.push('"'),
+                'u' => {
+                    let hex: String = (0..4)
+                        .map(|_| chars.next().map(|x| x.1))
+                        .collect::<Option<_>>()?;
+                    out.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                }
+                _ => return None,
+            },
+            c => out.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_err(text: &str) -> String {
+        Config::parse(text).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn strings() {
+        let config = Config::parse(concat!(
+            "message = \"a \\\"quoted\\\" \\u00e9\\n# not a comment\" # comment\n",
+            "colors = 'crate=red' # literal\n",
+        ))
+        .unwrap();
+        assert_eq!(
+            config.message.as_deref(),
+            Some("a \"quoted\" \u{e9}\n# not a comment")
+        );
+        assert_eq!(config.colors.as_deref(), Some("crate=red"));
+
+        let config = Config::parse(r"message = 'C:\path\no\escapes'").unwrap();
+        assert_eq!(config.message.as_deref(), Some(r"C:\path\no\escapes"));
+    }
+
+    #[test]
+    fn arrays() {
+        let config = Config::parse(concat!(
+            "hide = [\n",
+            "    \"tokio::*\", # runtime\n",
+            "    '*::poll',\n",
+            "]\n",
+            "source_roots = []\n",
+        ))
+        .unwrap();
+        assert_eq!(config.hide, ["tokio::*", "*::poll"]);
+        assert!(config.source_roots.is_empty());
+    }
+
+    #[test]
+    fn tables() {
+        let config = Config::parse(concat!(
+            "print_addresses = true\n",
+            "\n",
+            "[paths]\n",
+            "\"/build/app\" = \"/srv/app\"\n",
+            "'/build/lib' = '/srv/lib'\n",
+            "\n",
+            "[ output ]\n",
+            "stderr = false\n",
+            "file = \"/var/log/panics.log\"\n",
+        ))
+        .unwrap();
+        assert_eq!(config.print_addresses, Some(true));
+        assert_eq!(
+            config.path_maps,
+            [
+                ("/build/app".into(), "/srv/app".into()),
+                ("/build/lib".into(), "/srv/lib".into()),
+            ]
+        );
+        assert_eq!(config.output_stderr, Some(false));
+        assert_eq!(config.output_file, Some("/var/log/panics.log".into()));
+    }
+
+    #[test]
+    fn unknown_keys() {
+        assert_eq!(
+            parse_err("verbosity = \"full\"\ncolour = \"red\""),
+            "invalid config, line 2: unknown setting `colour`"
+        );
+        assert_eq!(
+            parse_err("[output]\nstdout = true"),
+            "invalid config, line 2: unknown setting `output.stdout`"
+        );
+        // `stderr` is only known in the `[output]` table.
+        assert_eq!(
+            parse_err("stderr = true"),
+            "invalid config, line 1: unknown setting `stderr`"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let cases = [
+            ("verbosity = full", "invalid value"),
+            (
+                "verbosity = \"loud\"",
+                "`verbosity` must be one of off, minimal, medium or full",
+            ),
+            (
+                "print_addresses = \"yes\"",
+                "`print_addresses` must be a boolean",
+            ),
+            ("hide = [\"a\", true]", "`hide` must be a list of strings"),
+            (
+                "theme = \"dark\"",
+                "`theme` must be one of classic, truecolor or adaptive",
+            ),
+            ("message = \"unterminated", "invalid value"),
+            ("message = \"a\" \"b\"", "trailing characters after value"),
+            ("[output", "unclosed table header"),
+            ("just words", "expected `key = value`"),
+            ("hide = [\"a\",", "unclosed array"),
+        ];
+        for (text, message) in cases {
+            assert_eq!(
+                parse_err(text),
+                format!("invalid config, line 1: {}", message),
+                "{}",
+                text
+            );
+        }
+    }
+}
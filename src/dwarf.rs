@@ -0,0 +1,106 @@
+//! Symbolication using `addr2line` directly on the executable's debug info.
+//!
+//! See [`Symbolizer::Addr2line`](crate::Symbolizer::Addr2line) for why one
+//! might want this over the `backtrace` crate's symbolication.
+
+use crate::Frame;
+use addr2line::gimli::{self, EndianSlice, RunTimeEndian};
+use object::{Object as _, ObjectSection as _, ObjectSegment as _};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufRead as _, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+type Reader = EndianSlice<'static, RunTimeEndian>;
+
+/// Debug info of the main executable.
+struct Module {
+    context: addr2line::Context<Reader>,
+    /// Difference between runtime and link-time addresses (ASLR slide).
+    bias: usize,
+}
+
+static MODULE: OnceLock<Option<Mutex<Module>>> = OnceLock::new();
+
+fn load() -> Option<Module> {
+    let exe = std::env::current_exe().ok()?;
+    // Loaded at most once per process and needed until its end anyway.
+    let data: &'static [u8] = Box::leak(std::fs::read(&exe).ok()?.into_boxed_slice());
+    let file = object::File::parse(data).ok()?;
+
+    let endian = match file.is_little_endian() {
+        true => RunTimeEndian::Little,
+        false => RunTimeEndian::Big,
+    };
+    let dwarf = gimli::Dwarf::load(|id| -> Result<Reader, gimli::Error> {
+        // Compressed sections aren't supported, those just come up empty.
+        let data = file
+            .section_by_name(id.name())
+            .and_then(|x| x.uncompressed_data().ok())
+            .unwrap_or_default();
+        let data = match data {
+            Cow::Borrowed(data) => data,
+            Cow::Owned(data) => Box::leak(data.into_boxed_slice()),
+        };
+        Ok(EndianSlice::new(data, endian))
+    })
+    .ok()?;
+
+    // The segment mapped from the start of the file tells where the binary
+    // was loaded relative to its link-time addresses.
+    let first_segment = file.segments().find(|x| x.file_range().0 == 0)?;
+    let load_address = mapping_start(&exe)?;
+    let bias = load_address.wrapping_sub(first_segment.address() as usize & !0xfff);
+
+    Some(Module {
+        context: addr2line::Context::from_dwarf(dwarf).ok()?,
+        bias,
+    })
+}
+
+/// Start of the mapping of the given file at offset 0, from `/proc/self/maps`.
+fn mapping_start(path: &Path) -> Option<usize> {
+    let maps = BufReader::new(File::open("/proc/self/maps").ok()?);
+    maps.lines().map_while(Result::ok).find_map(|line| {
+        // Format: `start-end perms offset dev inode path`.
+        let mut fields = line.split_whitespace();
+        let range = fields.next()?;
+        let offset = fields.nth(1)?;
+        let mapped = fields.nth(2)?;
+        if Path::new(mapped) != path || usize::from_str_radix(offset, 16).ok()? != 0 {
+            return None;
+        }
+        usize::from_str_radix(range.split('-').next()?, 16).ok()
+    })
+}
+
+/// Resolve the given return address to its frames, innermost (inlined) first.
+///
+/// Returns `None` for addresses outside of the main executable or without
+/// debug info, which are left to the `backtrace` crate.
+pub(crate) fn resolve(ip: usize) -> Option<Vec<Frame>> {
+    let module = MODULE.get_or_init(|| load().map(Mutex::new)).as_ref()?;
+    let module = module.lock().unwrap_or_else(|x| x.into_inner());
+
+    // Return addresses point behind the call instruction.
+    let probe = ip.checked_sub(1)?.checked_sub(module.bias)? as u64;
+    let mut lookup = module.context.find_frames(probe).skip_all_loads().ok()?;
+
+    let mut frames = Vec::new();
+    while let Ok(Some(frame)) = lookup.next() {
+        let name = frame.function.as_ref()?.raw_name().ok()?;
+        let location = frame.location;
+        frames.push(Frame {
+            n: 0,
+            name: Some(backtrace::SymbolName::new(name.as_bytes()).to_string()),
+            lineno: location.as_ref().and_then(|x| x.line),
+            colno: location.as_ref().and_then(|x| x.column),
+            filename: location.and_then(|x| x.file).map(PathBuf::from),
+            ip,
+            symbol_address: None,
+        });
+    }
+
+    (!frames.is_empty()).then_some(frames)
+}
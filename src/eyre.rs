@@ -0,0 +1,205 @@
+//! Hooks for applications using `eyre` without `color-eyre`.
+//!
+//! Follows the installation pattern of `color-eyre`: a [`HookBuilder`] is
+//! split into a [`PanicHandler`] and an [`EyreHook`], sharing the printer and
+//! thus the theme and verbosity settings. Panics are verbose depending on
+//! `RUST_BACKTRACE`, error reports depending on `RUST_LIB_BACKTRACE`, just
+//! like with `eyre`'s own handler.
+//!
+//! This crate doesn't depend on `eyre`, so hooking up the error reports takes
+//! a few lines of glue:
+//!
+//! ```rust,ignore
+//! use color_backtrace::eyre::{ErrorReport, HookBuilder};
+//!
+//! struct Handler(ErrorReport);
+//!
+//! impl eyre::EyreHandler for Handler {
+//!     fn debug(
+//!         &self,
+//!         error: &(dyn std::error::Error + 'static),
+//!         f: &mut core::fmt::Formatter<'_>,
+//!     ) -> core::fmt::Result {
+//!         self.0.debug(error, f)
+//!     }
+//! }
+//!
+//! let (panic_hook, eyre_hook) = HookBuilder::new().into_hooks();
+//! eyre::set_hook(Box::new(move |_| Box::new(Handler(eyre_hook.capture()))))?;
+//! panic_hook.install();
+//! ```
+
+use crate::printer::DeferredFrames;
+use crate::{default_output_stream, BacktracePrinter, Frame, Verbosity};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, IsTerminal as _};
+use std::sync::Arc;
+use termcolor::{Ansi, NoColor, WriteColor};
+
+/// Builder for a pair of panic and error report hooks sharing their settings.
+#[derive(Debug, Clone, Default)]
+pub struct HookBuilder {
+    printer: BacktracePrinter,
+}
+
+impl HookBuilder {
+    /// Alias for `HookBuilder::default`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Controls the printer used for both panics and error reports.
+    ///
+    /// Defaults to `BacktracePrinter::default()`.
+    pub fn printer(mut self, printer: BacktracePrinter) -> Self {
+        self.printer = printer;
+        self
+    }
+
+    /// Split the builder into the panic and the error report hook.
+    pub fn into_hooks(self) -> (PanicHandler, EyreHook) {
+        (
+            PanicHandler {
+                printer: self.printer.clone(),
+            },
+            EyreHook {
+                printer: self.printer,
+            },
+        )
+    }
+}
+
+/// The panic half of [`HookBuilder::into_hooks`].
+#[derive(Debug, Clone)]
+pub struct PanicHandler {
+    printer: BacktracePrinter,
+}
+
+impl PanicHandler {
+    /// Install the panic hook, printing to stderr.
+    pub fn install(self) {
+        std::panic::set_hook(self.into_panic_hook())
+    }
+
+    /// Create the panic hook, printing to stderr.
+    pub fn into_panic_hook(self) -> Box<crate::PanicHook> {
+        self.printer.into_panic_handler(default_output_stream())
+    }
+}
+
+/// The error report half of [`HookBuilder::into_hooks`].
+#[derive(Debug, Clone)]
+pub struct EyreHook {
+    printer: BacktracePrinter,
+}
+
+impl EyreHook {
+    /// Capture the context of an error being created, to be called from the
+    /// hook passed to `eyre::set_hook`.
+    ///
+    /// Errors are often created and handled without ever being printed, so
+    /// the backtrace is only symbolicated once needed. Below
+    /// `Verbosity::Medium`, no backtrace is captured at all.
+    pub fn capture(&self) -> ErrorReport {
+        let frames = if self.printer.current_verbosity() >= Verbosity::Medium {
+            self.printer.capture_deferred().map(|mut frames| {
+                // Drop the capturing machinery, up to and including this function.
+                let ours = frames.iter().position(|x| {
+                    x.name.as_deref().is_some_and(|name| {
+                        name.starts_with("color_backtrace::eyre::EyreHook::capture")
+                    })
+                });
+                if let Some(ours) = ours {
+                    frames.drain(..=ours);
+                }
+                for (frame, n) in frames.iter_mut().zip(1..) {
+                    frame.n = n;
+                }
+                frames
+            })
+        } else {
+            DeferredFrames::resolved(Vec::new())
+        };
+
+        ErrorReport {
+            printer: self.printer.clone(),
+            frames: Arc::new(frames),
+        }
+    }
+}
+
+/// Context of an error, rendered with the error in the style of the panics.
+#[derive(Debug, Clone)]
+pub struct ErrorReport {
+    printer: BacktracePrinter,
+    frames: Arc<DeferredFrames>,
+}
+
+impl ErrorReport {
+    /// Render the error, its causes and the backtrace, as for
+    /// `eyre::EyreHandler::debug`.
+    pub fn debug(&self, error: &(dyn Error + 'static), f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let color = std::io::stderr().is_terminal()
+            && !crate::is_plain_environment()
+            && crate::printer::ansi_supported();
+        let output = if color {
+            let mut out = Ansi::new(Vec::new());
+            self.print(error, &mut out).map_err(|_| fmt::Error)?;
+            out.into_inner()
+        } else {
+            let mut out = NoColor::new(Vec::new());
+            self.print(error, &mut out).map_err(|_| fmt::Error)?;
+            out.into_inner()
+        };
+        f.write_str(&String::from_utf8_lossy(&output))
+    }
+
+    /// The captured frames, innermost first. Empty if the error was created
+    /// below `Verbosity::Medium`.
+    pub fn frames(&self) -> &[Frame] {
+        self.frames.get()
+    }
+
+    fn print(&self, error: &(dyn Error + 'static), out: &mut impl WriteColor) -> io::Result<()> {
+        let colors = &self.printer.colors;
+
+        out.set_color(&colors.msg_loc_prefix)?;
+        write!(out, "{}", error)?;
+        out.reset()?;
+
+        let mut source = error.source();
+        if source.is_some() {
+            write!(out, "\n\nCaused by:")?;
+        }
+        for i in 0.. {
+            let cause = match source {
+                Some(cause) => cause,
+                None => break,
+            };
+            write!(out, "\n{:>4}: ", i)?;
+            out.set_color(&colors.msg_loc_prefix)?;
+            write!(out, "{}", cause)?;
+            out.reset()?;
+            source = cause.source();
+        }
+
+        // Not installed as panic handler, so this is the library verbosity.
+        let verbosity = self.printer.current_verbosity();
+        if verbosity == Verbosity::Off {
+            return Ok(());
+        }
+        if verbosity == Verbosity::Minimal {
+            write!(out, "\n\nBacktrace omitted. Run with ")?;
+            out.set_color(&colors.env_var)?;
+            write!(out, "RUST_LIB_BACKTRACE=1")?;
+            out.reset()?;
+            write!(out, " environment variable to display it.")?;
+            return Ok(());
+        }
+
+        writeln!(out, "\n")?;
+        self.printer.print_banner(out, " BACKTRACE ")?;
+        self.printer.print_frames(self.frames.get(), out)
+    }
+}
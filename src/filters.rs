@@ -0,0 +1,34 @@
+//! Ready-made frame filters for use with
+//! [`BacktracePrinter::add_frame_filter`](crate::BacktracePrinter::add_frame_filter).
+//!
+//! ```rust
+//! use color_backtrace::{filters, BacktracePrinter};
+//! use color_backtrace::filters::Regex;
+//!
+//! let printer = BacktracePrinter::new()
+//!     .add_frame_filter(filters::exclude_symbols(Regex::new(r"^tokio::").unwrap()))
+//!     .add_frame_filter(filters::exclude_files(Regex::new(r"/generated/").unwrap()));
+//! ```
+
+use crate::{FilterCallback, Frame};
+
+// Re-export regex so users don't have to depend on it themselves.
+pub use regex::Regex;
+
+/// Hide all frames whose symbol name matches the given regex.
+///
+/// Crate disambiguators and the hash suffix are part of the name matched
+/// against, so prefer patterns that don't rely on them.
+pub fn exclude_symbols(re: Regex) -> Box<FilterCallback> {
+    Box::new(move |frames: &mut Vec<&Frame>| {
+        frames.retain(|x| !matches!(&x.name, Some(name) if re.is_match(name)))
+    })
+}
+
+/// Hide all frames whose source file path matches the given regex.
+pub fn exclude_files(re: Regex) -> Box<FilterCallback> {
+    Box::new(move |frames: &mut Vec<&Frame>| {
+        frames
+            .retain(|x| !matches!(&x.filename, Some(file) if re.is_match(&file.to_string_lossy())))
+    })
+}
@@ -0,0 +1,66 @@
+//! Fast stack capture by walking the frame pointer chain.
+//!
+//! See [`CaptureMode::FramePointers`](crate::CaptureMode::FramePointers) for
+//! the trade-offs involved.
+
+/// Maximum number of frames captured.
+const MAX_FRAMES: usize = 256;
+
+/// Whether frame pointer walking is implemented for the target architecture.
+pub(crate) const SUPPORTED: bool = cfg!(any(target_arch = "x86_64", target_arch = "aarch64"));
+
+#[inline(always)]
+fn frame_pointer() -> usize {
+    let fp: usize;
+
+    // SAFETY: just reads a register.
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        std::arch::asm!("mov {}, rbp", out(reg) fp, options(nomem, nostack, preserves_flags));
+    }
+
+    // SAFETY: just reads a register.
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        std::arch::asm!("mov {}, x29", out(reg) fp, options(nomem, nostack, preserves_flags));
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        fp = 0;
+    }
+
+    fp
+}
+
+/// Collect the return addresses of all frames on the current stack.
+///
+/// # Safety
+///
+/// All code on the stack must have been compiled with frame pointers.
+/// Otherwise, this will read from arbitrary memory.
+#[inline(never)]
+pub(crate) unsafe fn capture() -> Vec<usize> {
+    let mut ips = Vec::with_capacity(64);
+    let mut fp = frame_pointer();
+
+    // On both supported architectures, the saved frame pointer of the caller
+    // is stored at `fp` and the return address right after it.
+    while fp != 0 && fp % std::mem::align_of::<usize>() == 0 && ips.len() < MAX_FRAMES {
+        let frame = fp as *const usize;
+        let next_fp = *frame;
+        let ret_addr = *frame.add(1);
+        if ret_addr == 0 {
+            break;
+        }
+        ips.push(ret_addr);
+
+        // The stack grows downwards: anything else indicates a broken chain.
+        if next_fp <= fp {
+            break;
+        }
+        fp = next_fp;
+    }
+
+    ips
+}
@@ -0,0 +1,161 @@
+//! End-user friendly panic reporting.
+//!
+//! Instead of confronting users of an application with a backtrace, a short
+//! apology is printed while the full report is written to a file that can be
+//! attached to a bug report.
+//!
+//! ```rust,no_run
+//! use color_backtrace::{human::EndUserReport, BacktracePrinter, Verbosity};
+//!
+//! EndUserReport::new(env!("CARGO_PKG_NAME"))
+//!     .homepage(env!("CARGO_PKG_REPOSITORY"))
+//!     .install(BacktracePrinter::new().verbosity(Verbosity::Full));
+//! ```
+
+use crate::printer::PanicInfo;
+use crate::BacktracePrinter;
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use termcolor::{ColorSpec, WriteColor};
+
+/// Number of reports written so far, keeping the file names of panics in
+/// the same second apart.
+static REPORT_SEQ: AtomicUsize = AtomicUsize::new(0);
+
+/// Builder for a panic handler writing reports to a file.
+#[derive(Debug, Clone)]
+pub struct EndUserReport {
+    name: String,
+    homepage: Option<String>,
+    report_dir: PathBuf,
+}
+
+impl EndUserReport {
+    /// Create a report handler for the application with the given name.
+    pub fn new(name: impl Into<String>) -> Self {
+        let name = name.into();
+        Self {
+            report_dir: crate::printer::user_state_dir().join(&name),
+            name,
+            homepage: None,
+        }
+    }
+
+    /// Controls the homepage users are pointed to for reporting the crash.
+    ///
+    /// Defaults to none.
+    pub fn homepage(mut self, url: impl Into<String>) -> Self {
+        self.homepage = Some(url.into());
+        self
+    }
+
+    /// Controls the directory the reports are written to. Reports are only
+    /// accessible to the current user.
+    ///
+    /// Defaults to a directory named after the application in the per-user
+    /// state directory: `$XDG_STATE_HOME` or `~/.local/state` on Unix,
+    /// `%LOCALAPPDATA%` on Windows.
+    pub fn report_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.report_dir = dir.into();
+        self
+    }
+
+    /// Install a panic handler writing reports rendered by the given printer.
+    pub fn install(self, printer: BacktracePrinter) {
+        std::panic::set_hook(self.into_panic_handler(printer))
+    }
+
+    /// Create a panic handler writing reports rendered by the given printer.
+    pub fn into_panic_handler(
+        self,
+        printer: BacktracePrinter,
+    ) -> Box<dyn Fn(&PanicInfo<'_>) + 'static + Sync + Send> {
+        printer.into_panic_handler_with_stream_for(move |_| ReportStream {
+            settings: self.clone(),
+            report: Vec::new(),
+        })
+    }
+
+    fn write_report(&self, report: &[u8]) -> io::Result<PathBuf> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |x| x.as_secs());
+        let path = self.report_dir.join(format!(
+            "{}-report-{}-{}-{}.txt",
+            self.name,
+            std::process::id(),
+            timestamp,
+            REPORT_SEQ.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        crate::printer::create_private_file(&path)?.write_all(report)?;
+        Ok(path)
+    }
+
+    fn print_apology(&self, path: &Path) -> io::Result<()> {
+        let mut out = io::stderr().lock();
+        writeln!(out, "Well, this is embarrassing.\n")?;
+        writeln!(
+            out,
+            "{} had a problem and crashed. A report with the details was written to:\n",
+            self.name
+        )?;
+        writeln!(out, "    {}\n", path.display())?;
+        match &self.homepage {
+            Some(url) => writeln!(out, "Please help us fix this by submitting it at {}.", url)?,
+            None => writeln!(
+                out,
+                "Please help us fix this by sending it to the developers."
+            )?,
+        }
+        writeln!(out, "Nothing was sent automatically. Thank you!")
+    }
+}
+
+/// Collects the report for a single panic and writes it out on drop.
+struct ReportStream {
+    settings: EndUserReport,
+    report: Vec<u8>,
+}
+
+impl io::Write for ReportStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.report.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl WriteColor for ReportStream {
+    fn supports_color(&self) -> bool {
+        false
+    }
+
+    fn set_color(&mut self, _: &ColorSpec) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for ReportStream {
+    fn drop(&mut self) {
+        let report = &self.report;
+        match self.settings.write_report(report) {
+            Ok(path) => {
+                let _ = self.settings.print_apology(&path);
+            }
+            // Better a technical report than none at all.
+            Err(_) => {
+                let _ = io::stderr().write_all(report);
+            }
+        }
+    }
+}
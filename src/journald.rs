@@ -0,0 +1,163 @@
+//! Reporting panics to the systemd journal.
+//!
+//! Every panic becomes a single structured journal entry with priority
+//! `CRIT` (2), the panic location in `CODE_FILE` / `CODE_LINE` and the plain
+//! text rendering of the panic in `MESSAGE`, using the native journal protocol.
+//!
+//! ```rust,no_run
+//! use color_backtrace::{journald::JournaldOutput, BacktracePrinter};
+//!
+//! JournaldOutput::new()
+//!     .mirror_to_stderr(true)
+//!     .install(BacktracePrinter::new());
+//! ```
+
+use crate::printer::PanicInfo;
+use crate::{default_output_stream, BacktracePrinter};
+use std::io::{self, Write as _};
+use std::os::unix::net::UnixDatagram;
+use termcolor::{ColorSpec, StandardStream, WriteColor};
+
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Builder for a panic handler logging to the systemd journal.
+#[derive(Debug, Clone, Default)]
+pub struct JournaldOutput {
+    identifier: Option<String>,
+    mirror_to_stderr: bool,
+}
+
+impl JournaldOutput {
+    /// Alias for `JournaldOutput::default`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Controls the `SYSLOG_IDENTIFIER` of the entries.
+    ///
+    /// Defaults to the file name of the current executable.
+    pub fn identifier(mut self, identifier: impl Into<String>) -> Self {
+        self.identifier = Some(identifier.into());
+        self
+    }
+
+    /// Controls whether the colored panic is additionally printed to stderr.
+    ///
+    /// Defaults to `false`.
+    pub fn mirror_to_stderr(mut self, val: bool) -> Self {
+        self.mirror_to_stderr = val;
+        self
+    }
+
+    /// Install a panic handler printing with the given printer to the journal.
+    pub fn install(self, printer: BacktracePrinter) {
+        std::panic::set_hook(self.into_panic_handler(printer))
+    }
+
+    /// Create a panic handler printing with the given printer to the journal.
+    pub fn into_panic_handler(
+        self,
+        printer: BacktracePrinter,
+    ) -> Box<dyn Fn(&PanicInfo<'_>) + 'static + Sync + Send> {
+        let identifier = self.identifier.or_else(|| {
+            let exe = std::env::current_exe().ok()?;
+            Some(exe.file_name()?.to_string_lossy().into_owned())
+        });
+
+        printer.into_panic_handler_with_stream_for(move |pi| JournaldStream {
+            identifier: identifier.clone(),
+            location: pi.location().map(|x| (x.file().to_owned(), x.line())),
+            message: Vec::new(),
+            mirror: self.mirror_to_stderr.then(default_output_stream),
+        })
+    }
+}
+
+/// Collects the output for a single panic and submits it on drop.
+struct JournaldStream {
+    identifier: Option<String>,
+    location: Option<(String, u32)>,
+    message: Vec<u8>,
+    mirror: Option<Box<StandardStream>>,
+}
+
+impl JournaldStream {
+    /// Serialize the entry as described in `systemd.journal-fields(7)` and the
+    /// native protocol documentation.
+    fn serialize(&self) -> Vec<u8> {
+        let mut entry = Vec::new();
+        let mut field = |name: &str, value: &[u8]| {
+            entry.extend_from_slice(name.as_bytes());
+            if value.contains(&b'\n') {
+                // Multi-line values are length-prefixed instead.
+                entry.push(b'\n');
+                entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+            } else {
+                entry.push(b'=');
+            }
+            entry.extend_from_slice(value);
+            entry.push(b'\n');
+        };
+
+        field("PRIORITY", b"2");
+        if let Some(identifier) = &self.identifier {
+            field("SYSLOG_IDENTIFIER", identifier.as_bytes());
+        }
+        if let Some((file, line)) = &self.location {
+            field("CODE_FILE", file.as_bytes());
+            field("CODE_LINE", line.to_string().as_bytes());
+        }
+        field("MESSAGE", &self.message);
+
+        entry
+    }
+}
+
+impl io::Write for JournaldStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.message.extend_from_slice(buf);
+        if let Some(mirror) = &mut self.mirror {
+            mirror.write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.mirror {
+            Some(mirror) => mirror.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl WriteColor for JournaldStream {
+    fn supports_color(&self) -> bool {
+        self.mirror.is_some()
+    }
+
+    fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
+        match &mut self.mirror {
+            Some(mirror) => mirror.set_color(spec),
+            None => Ok(()),
+        }
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        match &mut self.mirror {
+            Some(mirror) => mirror.reset(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for JournaldStream {
+    fn drop(&mut self) {
+        let sent = UnixDatagram::unbound()
+            .and_then(|sock| sock.send_to(&self.serialize(), JOURNAL_SOCKET));
+
+        // Don't lose the panic entirely if the journal isn't reachable.
+        if sent.is_err() && self.mirror.is_none() {
+            let _ = io::stderr().write_all(&self.message);
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! Output streams for mobile platforms, where stderr usually goes nowhere.
+//!
+//! On Android, panics are sent to logcat, on Apple platforms to the unified
+//! logging system (os_log). Output is passed on line by line since both
+//! systems truncate long messages.
+//!
+//! ```rust,ignore
+//! use color_backtrace::{mobile::LogcatStream, BacktracePrinter};
+//! BacktracePrinter::new().install(LogcatStream::new("my-app"));
+//! ```
+
+/// Priority of the emitted log messages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Priority {
+    Debug,
+    Info,
+    Warn,
+    Error,
+    #[default]
+    Fatal,
+}
+
+/// Buffers written data and passes on complete lines.
+#[derive(Debug, Default)]
+struct LineBuffer(Vec<u8>);
+
+impl LineBuffer {
+    fn write(&mut self, buf: &[u8], mut emit: impl FnMut(&str)) {
+        self.0.extend_from_slice(buf);
+        while let Some(pos) = self.0.iter().position(|&x| x == b'\n') {
+            let line: Vec<_> = self.0.drain(..=pos).collect();
+            emit(&String::from_utf8_lossy(&line[..pos]));
+        }
+    }
+
+    fn flush(&mut self, mut emit: impl FnMut(&str)) {
+        if !self.0.is_empty() {
+            emit(&String::from_utf8_lossy(&std::mem::take(&mut self.0)));
+        }
+    }
+}
+
+/// Implement `WriteColor` for a plain-text line-based stream.
+macro_rules! impl_line_stream {
+    ($ty:ty) => {
+        impl io::Write for $ty {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                let Self { target, lines } = self;
+                lines.write(buf, |x| Self::emit(target, x));
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                let Self { target, lines } = self;
+                lines.flush(|x| Self::emit(target, x));
+                Ok(())
+            }
+        }
+
+        impl Drop for $ty {
+            fn drop(&mut self) {
+                let _ = io::Write::flush(self);
+            }
+        }
+
+        impl WriteColor for $ty {
+            fn supports_color(&self) -> bool {
+                false
+            }
+
+            fn set_color(&mut self, _: &ColorSpec) -> io::Result<()> {
+                Ok(())
+            }
+
+            fn reset(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+    };
+}
+
+// ============================================================================================== //
+// [Android]                                                                                      //
+// ============================================================================================== //
+
+#[cfg(target_os = "android")]
+pub use self::android::LogcatStream;
+
+#[cfg(target_os = "android")]
+mod android {
+    use super::{LineBuffer, Priority};
+    use std::ffi::CString;
+    use std::io;
+    use std::os::raw::{c_char, c_int};
+    use termcolor::{ColorSpec, WriteColor};
+
+    #[link(name = "log")]
+    extern "C" {
+        fn __android_log_write(prio: c_int, tag: *const c_char, text: *const c_char) -> c_int;
+    }
+
+    #[derive(Debug)]
+    struct Target {
+        tag: CString,
+        priority: Priority,
+    }
+
+    /// Output stream writing to Android's logcat.
+    #[derive(Debug)]
+    pub struct LogcatStream {
+        target: Target,
+        lines: LineBuffer,
+    }
+
+    impl LogcatStream {
+        /// Create a stream logging with the given tag.
+        pub fn new(tag: &str) -> Self {
+            Self {
+                target: Target {
+                    tag: CString::new(tag.replace('\0', "")).unwrap_or_default(),
+                    priority: Priority::default(),
+                },
+                lines: LineBuffer::default(),
+            }
+        }
+
+        /// Controls the priority of the log messages.
+        ///
+        /// Defaults to `Priority::Fatal`.
+        pub fn priority(mut self, priority: Priority) -> Self {
+            self.target.priority = priority;
+            self
+        }
+
+        fn emit(target: &Target, line: &str) {
+            let prio = match target.priority {
+                Priority::Debug => 3,
+                Priority::Info => 4,
+                Priority::Warn => 5,
+                Priority::Error => 6,
+                Priority::Fatal => 7,
+            };
+
+            // Logcat drops empty messages, which would break up the layout.
+            let text = CString::new(if line.is_empty() { " " } else { line }.replace('\0', ""))
+                .unwrap_or_default();
+
+            // SAFETY: both strings are valid and NUL-terminated.
+            unsafe {
+                __android_log_write(prio, target.tag.as_ptr(), text.as_ptr());
+            }
+        }
+    }
+
+    impl_line_stream!(LogcatStream);
+}
+
+// ============================================================================================== //
+// [Apple]                                                                                        //
+// ============================================================================================== //
+
+#[cfg(target_vendor = "apple")]
+pub use self::apple::OsLogStream;
+
+#[cfg(target_vendor = "apple")]
+mod apple {
+    use super::{LineBuffer, Priority};
+    use oslog::{Level, OsLog};
+    use std::fmt;
+    use std::io;
+    use termcolor::{ColorSpec, WriteColor};
+
+    struct Target {
+        log: OsLog,
+        priority: Priority,
+    }
+
+    impl fmt::Debug for Target {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Target")
+                .field("priority", &self.priority)
+                .finish()
+        }
+    }
+
+    /// Output stream writing to Apple's unified logging system.
+    #[derive(Debug)]
+    pub struct OsLogStream {
+        target: Target,
+        lines: LineBuffer,
+    }
+
+    impl OsLogStream {
+        /// Create a stream logging to the given subsystem and category.
+        pub fn new(subsystem: &str, category: &str) -> Self {
+            Self::with_log(OsLog::new(subsystem, category))
+        }
+
+        /// Create a stream logging to the default log.
+        pub fn global() -> Self {
+            Self::with_log(OsLog::global())
+        }
+
+        fn with_log(log: OsLog) -> Self {
+            Self {
+                target: Target {
+                    log,
+                    priority: Priority::default(),
+                },
+                lines: LineBuffer::default(),
+            }
+        }
+
+        /// Controls the log type of the messages.
+        ///
+        /// os_log has no warning level: `Priority::Warn` maps to the default
+        /// log type. Defaults to `Priority::Fatal`, which maps to a fault.
+        pub fn priority(mut self, priority: Priority) -> Self {
+            self.target.priority = priority;
+            self
+        }
+
+        fn emit(target: &Target, line: &str) {
+            let level = match target.priority {
+                Priority::Debug => Level::Debug,
+                Priority::Info => Level::Info,
+                Priority::Warn => Level::Default,
+                Priority::Error => Level::Error,
+                Priority::Fatal => Level::Fault,
+            };
+            target.log.with_level(level, line);
+        }
+    }
+
+    impl_line_stream!(OsLogStream);
+}
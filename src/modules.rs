@@ -0,0 +1,216 @@
+//! Information about the modules (executable and shared libraries) loaded into
+//! the process, for printing addresses that can be symbolicated later.
+//!
+//! Only implemented for Linux and Android, by walking `/proc/self/maps`. On
+//! other platforms, no modules are reported. In sandbox mode, the modules are
+//! captured once at install time, see `BacktracePrinter::sandboxed`.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// A module mapped into the address space of the process.
+#[derive(Debug, Clone)]
+pub(crate) struct Module {
+    pub path: PathBuf,
+    pub base: usize,
+    pub end: usize,
+}
+
+impl Module {
+    /// File name of the module, for display.
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .unwrap_or(self.path.as_os_str())
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Modules along with their build IDs, captured before entering a sandbox,
+/// see [`preload`].
+static PRELOADED: OnceLock<Vec<(Module, Option<Vec<u8>>)>> = OnceLock::new();
+
+/// Capture the loaded modules and their build IDs, to be used from then on
+/// instead of accessing the filesystem.
+pub(crate) fn preload() {
+    PRELOADED.get_or_init(|| {
+        list_modules()
+            .into_iter()
+            .map(|x| {
+                let id = read_build_id(&x.path);
+                (x, id)
+            })
+            .collect()
+    });
+}
+
+/// List the file-backed modules loaded into the process, as preloaded if
+/// that happened.
+pub(crate) fn loaded_modules() -> Vec<Module> {
+    match PRELOADED.get() {
+        Some(preloaded) => preloaded.iter().map(|x| x.0.clone()).collect(),
+        None => list_modules(),
+    }
+}
+
+/// List the file-backed modules loaded into the process.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn list_modules() -> Vec<Module> {
+    let maps = match std::fs::read_to_string("/proc/self/maps") {
+        Ok(maps) => maps,
+        Err(_) => return Vec::new(),
+    };
+
+    // Modules along with whether any of their mappings is executable.
+    let mut modules: Vec<(Module, bool)> = Vec::new();
+    for line in maps.lines() {
+        // Format: `start-end perms offset dev inode path`.
+        let mut fields = line.split_whitespace();
+        let parsed = (|| {
+            let (start, end) = fields.next()?.split_once('-')?;
+            let executable = fields.next()?.contains('x');
+            let path = fields.nth(3)?;
+            let range = (
+                usize::from_str_radix(start, 16).ok()?,
+                usize::from_str_radix(end, 16).ok()?,
+            );
+            Some((range, executable, path))
+        })();
+
+        // Skip anonymous and pseudo mappings like `[stack]`.
+        let ((start, end), executable, path) = match parsed {
+            Some(x) if x.2.starts_with('/') => x,
+            _ => continue,
+        };
+
+        // Mappings of the same file are contiguous, merge them.
+        match modules.last_mut() {
+            Some((last, last_executable)) if last.path == Path::new(path) => {
+                last.end = end;
+                *last_executable |= executable;
+            }
+            _ => modules.push((
+                Module {
+                    path: PathBuf::from(path),
+                    base: start,
+                    end,
+                },
+                executable,
+            )),
+        }
+    }
+
+    // Files merely mapped for reading, e.g. to load debug info from, aren't
+    // modules.
+    modules.into_iter().filter(|x| x.1).map(|x| x.0).collect()
+}
+
+/// List the file-backed modules loaded into the process.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn list_modules() -> Vec<Module> {
+    Vec::new()
+}
+
+/// Modules as of the last lookup, see [`module_containing`].
+#[cfg(feature = "resolve-modules")]
+static CACHED_MODULES: std::sync::Mutex<Vec<Module>> = std::sync::Mutex::new(Vec::new());
+
+/// Find the loaded module containing the given address.
+///
+/// The modules are cached across calls, and only listed anew for addresses
+/// outside of all known ones, e.g. after a library was loaded.
+#[cfg(feature = "resolve-modules")]
+pub(crate) fn module_containing(addr: usize) -> Option<Module> {
+    let mut cached = CACHED_MODULES.lock().unwrap_or_else(|e| e.into_inner());
+    let find = |modules: &[Module]| {
+        modules
+            .iter()
+            .find(|x| (x.base..x.end).contains(&addr))
+            .cloned()
+    };
+    find(&cached).or_else(|| {
+        *cached = loaded_modules();
+        find(&cached)
+    })
+}
+
+/// The GNU build ID of the ELF file at the given path, as preloaded if that
+/// happened.
+pub(crate) fn build_id(path: &Path) -> Option<Vec<u8>> {
+    match PRELOADED.get() {
+        Some(preloaded) => preloaded.iter().find(|x| x.0.path == path)?.1.clone(),
+        None => read_build_id(path),
+    }
+}
+
+/// Read the GNU build ID from the ELF file at the given path.
+fn read_build_id(path: &Path) -> Option<Vec<u8>> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    const PT_NOTE: u32 = 4;
+    const NT_GNU_BUILD_ID: u32 = 3;
+
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut read_at = |offset: u64, len: usize| -> Option<Vec<u8>> {
+        let mut buf = vec![0; len];
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        file.read_exact(&mut buf).ok()?;
+        Some(buf)
+    };
+
+    // Only little endian ELF files are supported, which covers all
+    // mainstream targets.
+    let ident = read_at(0, 64)?;
+    if ident[..4] != *b"\x7fELF" || ident[5] != 1 {
+        return None;
+    }
+    let is_64 = ident[4] == 2;
+
+    let u16_at = |buf: &[u8], at: usize| u16::from_le_bytes([buf[at], buf[at + 1]]) as usize;
+    let u32_at = |buf: &[u8], at: usize| u32::from_le_bytes(buf[at..at + 4].try_into().unwrap());
+    let u64_at = |buf: &[u8], at: usize| u64::from_le_bytes(buf[at..at + 8].try_into().unwrap());
+
+    let (phoff, phentsize, phnum) = match is_64 {
+        true => (u64_at(&ident, 32), u16_at(&ident, 54), u16_at(&ident, 56)),
+        false => (
+            u32_at(&ident, 28) as u64,
+            u16_at(&ident, 42),
+            u16_at(&ident, 44),
+        ),
+    };
+    if phentsize < if is_64 { 56 } else { 32 } {
+        return None;
+    }
+    let phdrs = read_at(phoff, phentsize.checked_mul(phnum)?)?;
+
+    for phdr in phdrs.chunks_exact(phentsize) {
+        if u32_at(phdr, 0) != PT_NOTE {
+            continue;
+        }
+        let (offset, size) = match is_64 {
+            true => (u64_at(phdr, 8), u64_at(phdr, 32)),
+            false => (u32_at(phdr, 4) as u64, u32_at(phdr, 16) as u64),
+        };
+        let notes = read_at(offset, size.min(1 << 16) as usize)?;
+
+        // Notes are `namesz, descsz, type, name, desc`, padded to 4 bytes.
+        let mut pos = 0;
+        while pos + 12 <= notes.len() {
+            let namesz = u32_at(&notes, pos) as usize;
+            let descsz = u32_at(&notes, pos + 4) as usize;
+            let ty = u32_at(&notes, pos + 8);
+            let name_start = pos + 12;
+            let desc_start = name_start + (namesz + 3) / 4 * 4;
+            let desc_end = desc_start + descsz;
+            if desc_end > notes.len() {
+                break;
+            }
+            if ty == NT_GNU_BUILD_ID && notes[name_start..name_start + namesz] == *b"GNU\0" {
+                return Some(notes[desc_start..desc_end].to_vec());
+            }
+            pos = desc_start + (descsz + 3) / 4 * 4;
+        }
+    }
+    None
+}
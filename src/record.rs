@@ -0,0 +1,120 @@
+//! Panics as records in the OpenTelemetry data model.
+//!
+//! [`BacktracePrinter::on_panic_record`] passes every panic as a
+//! [`PanicRecord`] to a callback, in addition to the usual console output.
+//! The record follows the semantic conventions for exceptions: severity
+//! `ERROR`, the message as body and `exception.*` attributes, with
+//! `exception.stacktrace` holding the plain text rendering of the backtrace.
+//! It's a plain struct, so it's just as well suited for any other log or
+//! telemetry pipeline.
+//!
+//! ```rust
+//! use color_backtrace::{default_output_stream, BacktracePrinter};
+//!
+//! BacktracePrinter::new()
+//!     .on_panic_record(|record| {
+//!         let fields = record.attributes.iter();
+//!         let fields = fields.map(|(key, value)| format!("{}={:?}", key, value));
+//!         eprintln!("{} {}", record.severity_text, fields.collect::<Vec<_>>().join(" "));
+//!     })
+//!     .install(default_output_stream());
+//! ```
+//!
+//! This crate doesn't depend on `opentelemetry`. With it, handing the record
+//! to a logger from your provider takes a few lines of glue:
+//!
+//! ```rust,ignore
+//! use color_backtrace::{default_output_stream, BacktracePrinter};
+//! use opentelemetry::logs::{LogRecord as _, Logger as _, LoggerProvider as _, Severity};
+//!
+//! let logger = provider.logger("color-backtrace");
+//! let flush = provider.clone();
+//! BacktracePrinter::new()
+//!     .on_panic_record(move |record| {
+//!         let mut log = logger.create_log_record();
+//!         log.set_severity_number(Severity::Error);
+//!         log.set_severity_text(record.severity_text);
+//!         log.set_body(record.body.clone().into());
+//!         for (key, value) in &record.attributes {
+//!             log.add_attribute(*key, value.clone());
+//!         }
+//!         logger.emit(log);
+//!     })
+//!     // The process might be gone before the batch is exported otherwise.
+//!     .before_exit(move || drop(flush.force_flush()))
+//!     .install(default_output_stream());
+//! ```
+//!
+//! Or as event of the active span:
+//!
+//! ```rust,ignore
+//! use opentelemetry::{trace::get_active_span, KeyValue};
+//!
+//! BacktracePrinter::new()
+//!     .on_panic_record(|record| {
+//!         get_active_span(|span| {
+//!             let attributes = record.attributes.iter();
+//!             let attributes = attributes.map(|(k, v)| KeyValue::new(*k, v.clone()));
+//!             span.add_event(record.event_name, attributes.collect());
+//!         })
+//!     })
+//!     .install(default_output_stream());
+//! ```
+
+use crate::{BacktracePrinter, Frame, PanicReport};
+use termcolor::NoColor;
+
+/// `SeverityNumber` of OpenTelemetry's `ERROR` severity.
+const SEVERITY_ERROR: u8 = 17;
+
+/// A panic in terms of the OpenTelemetry data model, see the
+/// [module docs](self).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct PanicRecord {
+    /// Severity number of the log record, `17` (`ERROR`).
+    pub severity_number: u8,
+    /// Severity text of the log record, `ERROR`.
+    pub severity_text: &'static str,
+    /// Body of the log record, the panic message.
+    pub body: String,
+    /// Name of the span event, `exception`.
+    pub event_name: &'static str,
+    /// Attributes following the semantic conventions: `exception.type`,
+    /// `exception.message`, `exception.stacktrace`, `thread.name`,
+    /// `code.file.path`, `code.line.number` and `code.column.number`, the
+    /// latter ones only if known.
+    pub attributes: Vec<(&'static str, String)>,
+}
+
+impl PanicRecord {
+    /// Build the record, rendering the unfiltered `frames` the report was
+    /// created from.
+    pub(crate) fn new(printer: &BacktracePrinter, report: &PanicReport, frames: &[Frame]) -> Self {
+        let mut stacktrace = NoColor::new(Vec::new());
+        let _ = printer.print_frames(frames, &mut stacktrace);
+        let stacktrace = String::from_utf8_lossy(stacktrace.get_ref());
+
+        let mut attributes = vec![
+            ("exception.type", "panic".to_owned()),
+            ("exception.message", report.message.clone()),
+            ("exception.stacktrace", stacktrace.trim_end().to_owned()),
+        ];
+        if let Some(thread) = &report.thread {
+            attributes.push(("thread.name", thread.clone()));
+        }
+        if let Some(location) = &report.location {
+            attributes.push(("code.file.path", location.file.clone()));
+            attributes.push(("code.line.number", location.line.to_string()));
+            attributes.push(("code.column.number", location.column.to_string()));
+        }
+
+        Self {
+            severity_number: SEVERITY_ERROR,
+            severity_text: "ERROR",
+            body: report.message.clone(),
+            event_name: "exception",
+            attributes,
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Minimal printing path for hostile environments.
+//!
+//! The regular printer allocates, takes locks and reads files, none of which
+//! is allowed inside a signal handler and all of which may fail after memory
+//! corruption. [`MinimalPrinter`] gives up on symbols and snippets in return
+//! for robustness: it formats into a buffer on the stack and writes raw bytes
+//! directly to file descriptor 2, printing the message, the location and the
+//! raw frame addresses.
+//!
+//! Capturing the frames uses the system unwinder, which is not strictly
+//! async-signal-safe. It works well in practice (profilers do the same), but
+//! there are no guarantees.
+//!
+//! ```rust,no_run
+//! use color_backtrace::signal_safe::MinimalPrinter;
+//!
+//! extern "C" fn on_sigsegv(_: i32) {
+//!     MinimalPrinter::new().print("Segmentation fault");
+//!     std::process::abort();
+//! }
+//! ```
+
+use crate::printer::PanicInfo;
+use std::ffi::c_void;
+use std::fmt::{self, Write as _};
+use std::os::raw::c_int;
+
+extern "C" {
+    fn write(fd: c_int, buf: *const c_void, count: usize) -> isize;
+}
+
+const STDERR_FILENO: c_int = 2;
+
+const RED: &str = "\x1b[31m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Formats into a fixed buffer, writing it to stderr whenever it fills up.
+struct StackWriter {
+    buf: [u8; 512],
+    len: usize,
+}
+
+impl StackWriter {
+    fn new() -> Self {
+        Self {
+            buf: [0; 512],
+            len: 0,
+        }
+    }
+
+    fn flush(&mut self) {
+        let mut data = &self.buf[..self.len];
+        while !data.is_empty() {
+            // SAFETY: writes initialized bytes from a live buffer.
+            let n = unsafe { write(STDERR_FILENO, data.as_ptr().cast(), data.len()) };
+            if n <= 0 {
+                // Nothing sensible left to do, e.g. stderr is closed.
+                break;
+            }
+            data = &data[n as usize..];
+        }
+        self.len = 0;
+    }
+}
+
+impl fmt::Write for StackWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for chunk in s.as_bytes().chunks(self.buf.len()) {
+            if self.len + chunk.len() > self.buf.len() {
+                self.flush();
+            }
+            self.buf[self.len..self.len + chunk.len()].copy_from_slice(chunk);
+            self.len += chunk.len();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for StackWriter {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// Printer for signal handlers and other situations where neither
+/// allocating nor locking is an option.
+#[derive(Debug, Clone, Copy)]
+pub struct MinimalPrinter {
+    color: bool,
+    max_frames: usize,
+}
+
+impl Default for MinimalPrinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MinimalPrinter {
+    /// Create a printer with the default settings.
+    pub const fn new() -> Self {
+        Self {
+            color: true,
+            max_frames: 64,
+        }
+    }
+
+    /// Controls whether ANSI color codes are written.
+    ///
+    /// Defaults to `true`.
+    pub const fn color(mut self, val: bool) -> Self {
+        self.color = val;
+        self
+    }
+
+    /// Controls the maximum number of frames printed.
+    ///
+    /// Defaults to `64`.
+    pub const fn max_frames(mut self, n: usize) -> Self {
+        self.max_frames = n;
+        self
+    }
+
+    /// Print the given message along with the current backtrace.
+    pub fn print(&self, message: &str) {
+        self.print_with_location(message, None);
+    }
+
+    /// Print a panic along with the current backtrace.
+    pub fn print_panic_info(&self, pi: &PanicInfo) {
+        let payload = pi.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("Box<Any>");
+        let location = pi.location().map(|x| (x.file(), x.line()));
+        self.print_with_location(message, location);
+    }
+
+    /// Install a panic handler printing with this printer.
+    pub fn install(self) {
+        std::panic::set_hook(Box::new(move |pi| self.print_panic_info(pi)))
+    }
+
+    fn print_with_location(&self, message: &str, location: Option<(&str, u32)>) {
+        let mut out = StackWriter::new();
+        let _ = self.write_report(&mut out, message, location);
+    }
+
+    fn paint(&self, out: &mut StackWriter, color: &str) -> fmt::Result {
+        match self.color {
+            true => out.write_str(color),
+            false => Ok(()),
+        }
+    }
+
+    fn write_report(
+        &self,
+        out: &mut StackWriter,
+        message: &str,
+        location: Option<(&str, u32)>,
+    ) -> fmt::Result {
+        self.paint(out, RED)?;
+        out.write_str("The application panicked (crashed).")?;
+        self.paint(out, RESET)?;
+        out.write_str("\nMessage:  ")?;
+        self.paint(out, CYAN)?;
+        out.write_str(message)?;
+        self.paint(out, RESET)?;
+        out.write_str("\nLocation: ")?;
+        match location {
+            Some((file, line)) => write!(out, "{}:{}", file, line)?,
+            None => out.write_str("<unknown>")?,
+        }
+        out.write_str("\n\nFrames (unsymbolicated):\n")?;
+        self.write_frames(out)
+    }
+
+    #[cfg(feature = "backtrace")]
+    fn write_frames(&self, out: &mut StackWriter) -> fmt::Result {
+        let mut n = 0;
+        let mut result = Ok(());
+
+        // SAFETY: we neither allocate nor call into the backtrace crate
+        // from within the callback.
+        unsafe {
+            backtrace::trace_unsynchronized(|frame| {
+                n += 1;
+                result = writeln!(out, "{:>3}: {:#018x}", n, frame.ip() as usize);
+                result.is_ok() && n < self.max_frames
+            });
+        }
+        result
+    }
+
+    #[cfg(not(feature = "backtrace"))]
+    fn write_frames(&self, out: &mut StackWriter) -> fmt::Result {
+        out.write_str("  <requires the `backtrace` feature>\n")
+    }
+}
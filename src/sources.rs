@@ -0,0 +1,492 @@
+//! Locating source files for snippets.
+//!
+//! Deployed binaries usually run on machines without the sources they were
+//! built from, so snippets are missing exactly when they'd be most useful.
+//! [`embed_sources!`](crate::embed_sources) compiles selected files into the
+//! binary, and the printer consults them before looking at the filesystem.
+//! With the `remote-sources` feature, `RemoteSources` can additionally
+//! download the sources of registry dependencies from crates.io.
+//!
+//! ```rust
+//! use color_backtrace::{embed_sources, BacktracePrinter};
+//!
+//! let printer = BacktracePrinter::new().embed_sources(embed_sources!["src/lib.rs"]);
+//! ```
+
+use std::fmt;
+use std::path::Path;
+
+/// A source file embedded into the binary, see
+/// [`embed_sources!`](crate::embed_sources).
+#[derive(Clone, Copy)]
+pub struct EmbeddedSource {
+    /// Directory the path is relative to, usually the embedding crate's
+    /// manifest directory.
+    pub root: &'static str,
+    /// Path of the file, relative to `root`.
+    pub path: &'static str,
+    /// Contents of the file.
+    pub contents: &'static str,
+}
+
+impl fmt::Debug for EmbeddedSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmbeddedSource")
+            .field("root", &self.root)
+            .field("path", &self.path)
+            .field("len", &self.contents.len())
+            .finish()
+    }
+}
+
+/// Embed the given source files into the binary.
+///
+/// Paths are relative to the manifest directory of the crate invoking the
+/// macro. Evaluates to a `&'static [EmbeddedSource]` to be passed to
+/// [`BacktracePrinter::embed_sources`](crate::BacktracePrinter::embed_sources).
+///
+/// ```rust
+/// let sources: &[color_backtrace::sources::EmbeddedSource] =
+///     color_backtrace::embed_sources!["src/lib.rs", "src/printer.rs"];
+/// assert_eq!(sources.len(), 2);
+/// ```
+#[macro_export]
+macro_rules! embed_sources {
+    ($($path:literal),* $(,)?) => {
+        &[$(
+            $crate::sources::EmbeddedSource {
+                root: env!("CARGO_MANIFEST_DIR"),
+                path: $path,
+                contents: include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $path)),
+            }
+        ),*]
+    };
+}
+
+/// Find the embedded source for the file at the given path.
+///
+/// The path recorded in the debug info is matched against the full path, or
+/// the relative one for the workspace's own crates. If the binary was built
+/// elsewhere, the recorded path has to end in the embedding crate's directory
+/// followed by the relative path. Matching just the relative path would pick
+/// up the `src/lib.rs` of every dependency.
+pub(crate) fn find_embedded<'a>(
+    sources: &'a [EmbeddedSource],
+    path: &Path,
+) -> Option<&'a EmbeddedSource> {
+    sources
+        .iter()
+        .find(|x| Path::new(x.root).join(x.path) == path || Path::new(x.path) == path)
+        .or_else(|| {
+            sources.iter().find(|x| {
+                let dir = Path::new(x.root).file_name();
+                dir.is_some_and(|dir| path.ends_with(Path::new(dir).join(x.path)))
+            })
+        })
+}
+
+// ============================================================================================== //
+// [Remote sources]                                                                               //
+// ============================================================================================== //
+
+#[cfg(feature = "remote-sources")]
+pub use self::remote::RemoteSources;
+
+#[cfg(feature = "remote-sources")]
+mod remote {
+    use std::collections::HashSet;
+    use std::fmt;
+    use std::fs::{self, OpenOptions};
+    use std::io::{self, Write as _};
+    use std::path::{Component, Path, PathBuf};
+    use std::process::Command;
+    use std::sync::Mutex;
+
+    /// Callback downloading the contents at the given URL.
+    type FetchCallback = dyn Fn(&str) -> io::Result<Vec<u8>> + Send + Sync;
+
+    /// Resolver downloading the sources of registry dependencies from
+    /// crates.io, for snippets of dependency frames whose sources aren't
+    /// extracted locally.
+    ///
+    /// Packages are fetched at most once per process, verified against the
+    /// checksum published in the crates.io index and extracted into a cache
+    /// directory that is consulted first. Downloading happens from within the
+    /// panic handler, so this is strictly opt-in via
+    /// [`BacktracePrinter::remote_sources`](crate::BacktracePrinter::remote_sources).
+    pub struct RemoteSources {
+        cache_dir: Option<PathBuf>,
+        fetch: Box<FetchCallback>,
+        attempted: Mutex<HashSet<String>>,
+    }
+
+    impl fmt::Debug for RemoteSources {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("RemoteSources")
+                .field("cache_dir", &self.cache_dir)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl Default for RemoteSources {
+        fn default() -> Self {
+            Self {
+                cache_dir: user_cache_dir().map(|x| x.join("color-backtrace").join("sources")),
+                fetch: Box::new(fetch_with_curl),
+                attempted: Mutex::new(HashSet::new()),
+            }
+        }
+    }
+
+    impl RemoteSources {
+        /// Alias for `RemoteSources::default`.
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Controls the directory downloaded packages are extracted to.
+        ///
+        /// The directory is created accessible to the current user only, and
+        /// not used if it is writable by others.
+        ///
+        /// Defaults to `color-backtrace/sources` in the user's cache
+        /// directory: `$XDG_CACHE_HOME`, `~/.cache` or `%LOCALAPPDATA%`. If
+        /// none of them is known, nothing is downloaded.
+        pub fn cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+            self.cache_dir = Some(dir.into());
+            self
+        }
+
+        /// Controls how packages are downloaded, e.g. to use the HTTP client
+        /// the application already depends on.
+        ///
+        /// Defaults to invoking `curl` with a timeout of 10 seconds.
+        pub fn fetcher(
+            mut self,
+            fetch: impl Fn(&str) -> io::Result<Vec<u8>> + Send + Sync + 'static,
+        ) -> Self {
+            self.fetch = Box::new(fetch);
+            self
+        }
+
+        /// Locate the given file of a crate, downloading the package if it
+        /// isn't cached yet.
+        pub(crate) fn locate(&self, name: &str, version: &str, relative: &Path) -> Option<PathBuf> {
+            let cache_dir = self.cache_dir.as_deref()?;
+            let key = format!("{}-{}", name, version);
+            let crate_dir = cache_dir.join(&key);
+            let path = crate_dir.join(relative);
+            if path.is_file() {
+                return Some(path);
+            }
+
+            // Only try once, and don't hold the lock while downloading:
+            // concurrent panics go without the snippet instead of waiting.
+            let first_attempt = self
+                .attempted
+                .lock()
+                .unwrap_or_else(|x| x.into_inner())
+                .insert(key.clone());
+            if !first_attempt || crate_dir.exists() {
+                return None;
+            }
+
+            self.download(name, version, cache_dir).ok()?;
+            path.is_file().then_some(path)
+        }
+
+        fn download(&self, name: &str, version: &str, cache_dir: &Path) -> io::Result<()> {
+            ensure_private_dir(cache_dir)?;
+
+            let index = (self.fetch)(&index_url(name))?;
+            let checksum = index_checksum(&String::from_utf8_lossy(&index), version)
+                .ok_or_else(|| invalid("version not found in the index"))?;
+            let url = format!(
+                "https://static.crates.io/crates/{name}/{name}-{version}.crate",
+                name = name,
+                version = version
+            );
+            let package = (self.fetch)(&url)?;
+            if hex(&sha256(&package)) != checksum {
+                return Err(invalid("checksum mismatch"));
+            }
+
+            // Extract into a fresh directory first, so an interrupted
+            // extraction doesn't leave a partial package behind.
+            let key = format!("{}-{}", name, version);
+            let staging = cache_dir.join(format!(".{}.{}", key, std::process::id()));
+            let _ = fs::remove_dir_all(&staging);
+            let result = extract_crate(&package, &key, &staging)
+                .and_then(|()| fs::rename(staging.join(&key), cache_dir.join(&key)));
+            let _ = fs::remove_dir_all(&staging);
+            result
+        }
+    }
+
+    /// The per-user cache directory.
+    fn user_cache_dir() -> Option<PathBuf> {
+        let non_empty = |var| {
+            std::env::var_os(var)
+                .filter(|x| !x.is_empty())
+                .map(PathBuf::from)
+        };
+        if cfg!(windows) {
+            return non_empty("LOCALAPPDATA");
+        }
+        non_empty("XDG_CACHE_HOME").or_else(|| Some(non_empty("HOME")?.join(".cache")))
+    }
+
+    /// Create the directory accessible to the current user only, or make
+    /// sure an existing one isn't writable by others.
+    fn ensure_private_dir(dir: &Path) -> io::Result<()> {
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder.create(dir)?;
+
+        let metadata = fs::symlink_metadata(dir)?;
+        if !metadata.is_dir() {
+            return Err(invalid("cache path is not a directory"));
+        }
+        #[cfg(unix)]
+        if std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o022 != 0 {
+            return Err(invalid("cache directory is writable by others"));
+        }
+        Ok(())
+    }
+
+    /// URL of the crate's file in the crates.io sparse index.
+    fn index_url(name: &str) -> String {
+        let name = name.to_ascii_lowercase();
+        let prefix = match name.len() {
+            1 => "1".to_owned(),
+            2 => "2".to_owned(),
+            3 => format!("3/{}", &name[..1]),
+            _ => format!("{}/{}", &name[..2], &name[2..4]),
+        };
+        format!("https://index.crates.io/{}/{}", prefix, name)
+    }
+
+    /// Find the package checksum of the given version in a sparse index file,
+    /// one JSON object per version and line.
+    fn index_checksum(index: &str, version: &str) -> Option<String> {
+        let string_field = |line: &str, key: &str| {
+            let key = format!("\"{}\"", key);
+            let rest = &line[line.find(&key)? + key.len()..];
+            let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+            let rest = rest.strip_prefix('"')?;
+            Some(rest[..rest.find('"')?].to_owned())
+        };
+        let line = index
+            .lines()
+            .find(|x| string_field(x, "vers").as_deref() == Some(version))?;
+        let checksum = string_field(line, "cksum")?;
+        (checksum.len() == 64).then(|| checksum.to_ascii_lowercase())
+    }
+
+    fn fetch_with_curl(url: &str) -> io::Result<Vec<u8>> {
+        let output = Command::new("curl")
+            .args(["--silent", "--fail", "--location", "--max-time", "10", url])
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("curl failed with {}", output.status),
+            ));
+        }
+        Ok(output.stdout)
+    }
+
+    fn invalid(msg: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, msg)
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|x| format!("{:02x}", x)).collect()
+    }
+
+    /// SHA-256 (FIPS 180-4), as used for the checksums in the index.
+    fn sha256(data: &[u8]) -> [u8; 32] {
+        const K: [u32; 64] = [
+            0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+            0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+            0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+            0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+            0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+            0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+            0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+            0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+            0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+            0xc67178f2,
+        ];
+        let mut h: [u32; 8] = [
+            0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+            0x5be0cd19,
+        ];
+
+        let mut message = data.to_vec();
+        message.push(0x80);
+        while message.len() % 64 != 56 {
+            message.push(0);
+        }
+        message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+        for block in message.chunks_exact(64) {
+            let mut w = [0u32; 64];
+            for (i, word) in block.chunks_exact(4).enumerate() {
+                w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+            }
+            for i in 16..64 {
+                let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+                let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+                w[i] = w[i - 16]
+                    .wrapping_add(s0)
+                    .wrapping_add(w[i - 7])
+                    .wrapping_add(s1);
+            }
+
+            let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+            for i in 0..64 {
+                let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+                let ch = (e & f) ^ (!e & g);
+                let t1 = hh
+                    .wrapping_add(s1)
+                    .wrapping_add(ch)
+                    .wrapping_add(K[i])
+                    .wrapping_add(w[i]);
+                let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+                let maj = (a & b) ^ (a & c) ^ (b & c);
+                let t2 = s0.wrapping_add(maj);
+                hh = g;
+                g = f;
+                f = e;
+                e = d.wrapping_add(t1);
+                d = c;
+                c = b;
+                b = a;
+                a = t1.wrapping_add(t2);
+            }
+            for (x, y) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+                *x = x.wrapping_add(y);
+            }
+        }
+
+        let mut digest = [0; 32];
+        for (out, word) in digest.chunks_exact_mut(4).zip(h) {
+            out.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+    /// Strip the gzip container (RFC 1952) and inflate the contents.
+    fn gunzip(data: &[u8]) -> io::Result<Vec<u8>> {
+        const FHCRC: u8 = 1 << 1;
+        const FEXTRA: u8 = 1 << 2;
+        const FNAME: u8 = 1 << 3;
+        const FCOMMENT: u8 = 1 << 4;
+
+        if data.len() < 18 || data[..3] != [0x1f, 0x8b, 8] {
+            return Err(invalid("not a gzip file"));
+        }
+
+        let flags = data[3];
+        let mut pos = 10;
+        if flags & FEXTRA != 0 {
+            let len = data.get(pos..pos + 2).ok_or_else(|| invalid("truncated"))?;
+            pos += 2 + u16::from_le_bytes([len[0], len[1]]) as usize;
+        }
+        for flag in [FNAME, FCOMMENT] {
+            if flags & flag != 0 {
+                let len = data[pos.min(data.len())..]
+                    .iter()
+                    .position(|&x| x == 0)
+                    .ok_or_else(|| invalid("truncated"))?;
+                pos += len + 1;
+            }
+        }
+        if flags & FHCRC != 0 {
+            pos += 2;
+        }
+
+        let deflated = data.get(pos..).ok_or_else(|| invalid("truncated"))?;
+        miniz_oxide::inflate::decompress_to_vec(deflated)
+            .map_err(|_| invalid("corrupt deflate stream"))
+    }
+
+    /// Extract the regular files of a `.crate` package (a gzipped tarball),
+    /// all of which must be below the `prefix` directory, into `dest`.
+    ///
+    /// Entries with other paths, or with `..` or absolute paths, fail the
+    /// extraction. Links and other special entries are skipped, and existing
+    /// files are never overwritten.
+    fn extract_crate(data: &[u8], prefix: &str, dest: &Path) -> io::Result<()> {
+        let tar = gunzip(data)?;
+
+        let field = |header: &[u8], range: std::ops::Range<usize>| {
+            let raw = &header[range];
+            let end = raw.iter().position(|&x| x == 0).unwrap_or(raw.len());
+            String::from_utf8_lossy(&raw[..end]).into_owned()
+        };
+
+        let mut long_name = None;
+        let mut pos = 0;
+        while let Some(header) = tar.get(pos..pos + 512) {
+            if header.iter().all(|&x| x == 0) {
+                break;
+            }
+
+            let size = usize::from_str_radix(field(header, 124..136).trim(), 8)
+                .map_err(|_| invalid("bad entry size"))?;
+            let contents = tar
+                .get(pos + 512..pos + 512 + size)
+                .ok_or_else(|| invalid("truncated"))?;
+            pos += 512 + (size + 511) / 512 * 512;
+
+            // Only POSIX headers have a name prefix, GNU ones use the space
+            // for other purposes.
+            let is_ustar = header[257..263] == *b"ustar\0";
+            let name = match long_name.take() {
+                Some(name) => name,
+                None => match field(header, 345..500) {
+                    prefix if is_ustar && !prefix.is_empty() => {
+                        format!("{}/{}", prefix, field(header, 0..100))
+                    }
+                    _ => field(header, 0..100),
+                },
+            };
+
+            match header[156] {
+                // GNU long name, applying to the next entry.
+                b'L' => {
+                    let end = contents.iter().position(|&x| x == 0).unwrap_or(size);
+                    long_name = Some(String::from_utf8_lossy(&contents[..end]).into_owned());
+                }
+                b'0' | 0 => {
+                    // Don't let the package write outside of its directory.
+                    let relative = Path::new(&name);
+                    let confined = relative
+                        .components()
+                        .all(|x| matches!(x, Component::Normal(_)))
+                        && relative.starts_with(prefix);
+                    if !confined {
+                        return Err(invalid("bad entry path"));
+                    }
+
+                    let path = dest.join(relative);
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .open(path)?
+                        .write_all(contents)?;
+                }
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
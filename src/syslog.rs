@@ -0,0 +1,208 @@
+//! Reporting panics to the system log.
+//!
+//! [`SyslogStream`] sends every line of the panic as a separate message in the
+//! classic BSD syslog format (`<PRI>TAG[PID]: MSG`), either to the local
+//! syslog daemon or via UDP.
+//!
+//! ```rust,no_run
+//! use color_backtrace::{syslog::SyslogStream, BacktracePrinter};
+//!
+//! BacktracePrinter::new().install(SyslogStream::local().unwrap());
+//! ```
+
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use termcolor::{ColorSpec, WriteColor};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+
+/// Syslog facility, as defined by RFC 5424.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Facility {
+    Kernel = 0,
+    #[default]
+    User = 1,
+    Mail = 2,
+    Daemon = 3,
+    Auth = 4,
+    Syslog = 5,
+    Lpr = 6,
+    News = 7,
+    Uucp = 8,
+    Cron = 9,
+    AuthPriv = 10,
+    Ftp = 11,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+/// Syslog severity, as defined by RFC 5424.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Severity {
+    Emergency = 0,
+    Alert = 1,
+    #[default]
+    Critical = 2,
+    Error = 3,
+    Warning = 4,
+    Notice = 5,
+    Informational = 6,
+    Debug = 7,
+}
+
+#[derive(Debug)]
+enum Transport {
+    #[cfg(unix)]
+    Unix(UnixDatagram),
+    Udp(UdpSocket),
+}
+
+/// Output stream sending plain-text panics to syslog.
+///
+/// Written data is buffered and sent out line by line on flush, which the
+/// panic handler does after every panic.
+#[derive(Debug)]
+pub struct SyslogStream {
+    transport: Transport,
+    facility: Facility,
+    severity: Severity,
+    tag: String,
+    buf: Vec<u8>,
+}
+
+impl SyslogStream {
+    fn new(transport: Transport) -> Self {
+        let tag = std::env::current_exe()
+            .ok()
+            .and_then(|x| Some(x.file_name()?.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "rust".to_owned());
+
+        Self {
+            transport,
+            facility: Facility::default(),
+            severity: Severity::default(),
+            tag,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Connect to the local syslog daemon via `/dev/log`.
+    #[cfg(unix)]
+    pub fn local() -> io::Result<Self> {
+        Self::unix("/dev/log")
+    }
+
+    /// Connect to a syslog daemon listening on the given unix socket.
+    #[cfg(unix)]
+    pub fn unix(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+        let sock = UnixDatagram::unbound()?;
+        sock.connect(path)?;
+        Ok(Self::new(Transport::Unix(sock)))
+    }
+
+    /// Send messages to a syslog server via UDP.
+    ///
+    /// The first of the server's addresses a socket can be connected to is
+    /// used, over IPv4 or IPv6 as appropriate.
+    pub fn udp(server: impl ToSocketAddrs) -> io::Result<Self> {
+        let mut last_error = None;
+        for addr in server.to_socket_addrs()? {
+            let local: SocketAddr = match addr {
+                SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+                SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+            };
+            match UdpSocket::bind(local).and_then(|sock| sock.connect(addr).map(|()| sock)) {
+                Ok(sock) => return Ok(Self::new(Transport::Udp(sock))),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "could not resolve to any addresses",
+            )
+        }))
+    }
+
+    /// Controls the facility of the messages.
+    ///
+    /// Defaults to `Facility::User`.
+    pub fn facility(mut self, facility: Facility) -> Self {
+        self.facility = facility;
+        self
+    }
+
+    /// Controls the severity of the messages.
+    ///
+    /// Defaults to `Severity::Critical`.
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Controls the tag (program name) of the messages.
+    ///
+    /// Defaults to the file name of the current executable.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = tag.into();
+        self
+    }
+
+    fn send(&self, line: &str) -> io::Result<()> {
+        let msg = format!(
+            "<{}>{}[{}]: {}",
+            self.facility as u8 * 8 + self.severity as u8,
+            self.tag,
+            std::process::id(),
+            line,
+        );
+
+        match &self.transport {
+            #[cfg(unix)]
+            Transport::Unix(sock) => sock.send(msg.as_bytes()).map(drop),
+            Transport::Udp(sock) => sock.send(msg.as_bytes()).map(drop),
+        }
+    }
+}
+
+impl io::Write for SyslogStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let buf = std::mem::take(&mut self.buf);
+        String::from_utf8_lossy(&buf)
+            .lines()
+            .filter(|x| !x.trim().is_empty())
+            .try_for_each(|x| self.send(x))
+    }
+}
+
+impl Drop for SyslogStream {
+    fn drop(&mut self) {
+        let _ = io::Write::flush(self);
+    }
+}
+
+impl WriteColor for SyslogStream {
+    fn supports_color(&self) -> bool {
+        false
+    }
+
+    fn set_color(&mut self, _: &ColorSpec) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+//! Re-rendering panics from text output, e.g. CI logs and crash reports.
+//!
+//! [`rewrite`] scans text for the panic messages and backtraces printed by
+//! the default Rust panic handler and replaces them with this crate's
+//! rendering, applying the printer's colors, frame filters and snippets.
+//! Everything else is passed through unchanged.
+//!
+//! The `cli` feature builds a `color-backtrace` binary around it:
+//!
+//! ```text
+//! $ RUST_BACKTRACE=1 cargo test 2>&1 | color-backtrace
+//! $ color-backtrace --full ci-log.txt
+//! ```
+
+use crate::{BacktracePrinter, Frame, PanicHeader, PanicLocation, Renderer, TermRenderer};
+use std::borrow::Cow;
+use std::io::{self, BufRead};
+use termcolor::WriteColor;
+
+/// Parse frames from a backtrace in the text format of `std`, both short and
+/// full.
+///
+/// ```rust
+/// let frames = color_backtrace::text::parse_frames(
+///     "   0: my_app::main\n             at ./src/main.rs:4:5",
+/// );
+/// assert_eq!(frames[0].name.as_deref(), Some("my_app::main"));
+/// assert_eq!(frames[0].lineno, Some(4));
+/// ```
+pub fn parse_frames(text: &str) -> Vec<Frame> {
+    let mut frames: Vec<Frame> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_start();
+        if let Some(location) = line.strip_prefix("at ") {
+            let frame = match frames.last_mut() {
+                Some(frame) if frame.filename.is_none() => frame,
+                _ => continue,
+            };
+
+            // `file:line:col`, where the file may contain colons itself.
+            let mut parts = location.rsplitn(3, ':');
+            let colno = parts.next().and_then(|x| x.parse().ok());
+            let lineno = parts.next().and_then(|x| x.parse().ok());
+            match (parts.next(), lineno, colno) {
+                (Some(file), Some(_), Some(_)) => {
+                    frame.filename = Some(file.into());
+                    frame.lineno = lineno;
+                    frame.colno = colno;
+                }
+                _ => frame.filename = Some(location.into()),
+            }
+        } else if let Some((idx, rest)) = line.split_once(": ") {
+            if !is_frame_index(idx) {
+                continue;
+            }
+
+            let rest = rest.trim_start();
+            let parsed_ip = rest
+                .strip_prefix("0x")
+                .and_then(|x| x.split_once(" - "))
+                .and_then(|(ip, name)| Some((usize::from_str_radix(ip, 16).ok()?, name)));
+            let (ip, name) = match parsed_ip {
+                Some((ip, name)) => (ip, name),
+                None => (frames.last().map_or(0, |x| x.ip), rest),
+            };
+
+            frames.push(Frame {
+                n: frames.len() + 1,
+                name: Some(name.to_owned()),
+                lineno: None,
+                colno: None,
+                filename: None,
+                ip,
+                symbol_address: None,
+            });
+        }
+    }
+    frames
+}
+
+/// Copy `input` to `out`, re-rendering the panics and backtraces found in it
+/// with the given printer.
+///
+/// The printer's library verbosity, see [`BacktracePrinter::lib_verbosity`],
+/// controls whether source snippets are printed.
+pub fn rewrite(
+    printer: &BacktracePrinter,
+    input: impl BufRead,
+    out: &mut impl WriteColor,
+) -> io::Result<()> {
+    let mut lines = Lines(input).peekable();
+    while let Some(line) = lines.next() {
+        let line = line?;
+        let trimmed = line.trim();
+
+        if let Some((thread, rest)) = parse_panic_line(trimmed) {
+            let (message, location) = match rest.strip_suffix(':') {
+                // Since Rust 1.73, the message follows on the next lines.
+                Some(location) => {
+                    let mut message = Vec::new();
+                    while let Some(Ok(next)) =
+                        lines.next_if(|x| x.as_ref().is_ok_and(|x| !ends_message(x)))
+                    {
+                        message.push(next);
+                    }
+                    (Cow::Owned(message.join("\n")), parse_location(location))
+                }
+                // Before, it was quoted on the same line.
+                None => match rest.rsplit_once("', ") {
+                    Some((message, location)) => (
+                        Cow::Borrowed(message.trim_start_matches('\'')),
+                        parse_location(location),
+                    ),
+                    None => (Cow::Borrowed(rest), None),
+                },
+            };
+
+            TermRenderer::new(printer, &mut *out).render_header(&PanicHeader {
+                title: &printer.message,
+                notes: vec![format!("thread '{}'", thread)],
+                message,
+                location,
+            })?;
+            continue;
+        }
+
+        if trimmed == "stack backtrace:" {
+            let mut trace = String::new();
+            while let Some(Ok(next)) = lines.next_if(|x| x.as_ref().is_ok_and(|x| is_frame_line(x)))
+            {
+                trace.push_str(&next);
+                trace.push('\n');
+            }
+            // Refers to the options of the default handler, not ours.
+            lines.next_if(|x| {
+                x.as_ref()
+                    .is_ok_and(|x| x.starts_with("note: Some details are omitted"))
+            });
+
+            writeln!(out)?;
+            printer.print_banner(out, " BACKTRACE ")?;
+            printer.print_frames(&parse_frames(&trace), out)?;
+            continue;
+        }
+
+        writeln!(out, "{}", line)?;
+    }
+    out.flush()
+}
+
+/// Lines of the input, with invalid UTF-8 replaced instead of failing.
+struct Lines<R>(R);
+
+impl<R: BufRead> Iterator for Lines<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = Vec::new();
+        match self.0.read_until(b'\n', &mut buf) {
+            Ok(0) => None,
+            Ok(_) => {
+                if buf.ends_with(b"\n") {
+                    buf.pop();
+                }
+                if buf.ends_with(b"\r") {
+                    buf.pop();
+                }
+                Some(Ok(String::from_utf8_lossy(&buf).into_owned()))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Split `thread 'main' panicked at ...` into the thread name and the rest.
+fn parse_panic_line(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix("thread '")?;
+    let (thread, rest) = rest.split_once('\'')?;
+    let rest = rest.trim_start();
+    // Some versions include the thread id: `thread 'main' (1234) panicked`.
+    let rest = match rest.strip_prefix('(') {
+        Some(rest) => rest.split_once(')')?.1.trim_start(),
+        None => rest,
+    };
+    Some((thread, rest.strip_prefix("panicked at ")?))
+}
+
+/// Parse a `file:line:col` location.
+fn parse_location(text: &str) -> Option<PanicLocation> {
+    let mut parts = text.trim().rsplitn(3, ':');
+    let column = parts.next()?.parse().ok()?;
+    let line = parts.next()?.parse().ok()?;
+    Some(PanicLocation {
+        file: parts.next()?.to_owned(),
+        line,
+        column,
+    })
+}
+
+/// Whether the line ends a multi-line panic message.
+fn ends_message(line: &str) -> bool {
+    line.is_empty()
+        || line.starts_with("note: ")
+        || line.trim() == "stack backtrace:"
+        || parse_panic_line(line.trim()).is_some()
+}
+
+/// Whether the line belongs to a backtrace: a numbered frame or a location.
+fn is_frame_line(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("at ")
+        || line
+            .split_once(": ")
+            .is_some_and(|(idx, _)| is_frame_index(idx))
+}
+
+fn is_frame_index(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c.is_ascii_digit())
+}
@@ -0,0 +1,238 @@
+//! Capturing the stacks of all other threads of the process.
+//!
+//! This is only supported on Linux, with the `all-threads` feature enabled.
+//! Other threads are interrupted with a real-time signal whose handler walks
+//! the stack of the interrupted thread and stores the raw instruction pointers
+//! in a static buffer. Symbolication then happens on the panicking thread.
+//!
+//! Walking the stack from within a signal handler is not strictly
+//! async-signal-safe. It works well in practice (profilers do the same), but
+//! since we only do this while the process is already panicking, a failure
+//! here is considered acceptable.
+
+use crate::Frame;
+
+/// Backtrace of a thread other than the panicking one.
+pub(crate) struct ThreadTrace {
+    pub tid: i64,
+    pub name: Option<String>,
+    pub frames: Vec<Frame>,
+}
+
+#[cfg(all(feature = "all-threads", target_os = "linux"))]
+pub(crate) use imp::capture_other_threads;
+
+#[cfg(not(all(feature = "all-threads", target_os = "linux")))]
+pub(crate) fn capture_other_threads(_: crate::Symbolizer) -> Vec<ThreadTrace> {
+    Vec::new()
+}
+
+#[cfg(all(feature = "all-threads", target_os = "linux"))]
+mod imp {
+    use super::ThreadTrace;
+    use crate::{Frame, Symbolizer};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    /// Maximum number of frames captured per thread.
+    const MAX_FRAMES: usize = 128;
+
+    /// How long to wait for a thread to respond to the signal.
+    const TIMEOUT: Duration = Duration::from_millis(100);
+
+    #[allow(clippy::declare_interior_mutable_const)]
+    const ZERO: AtomicUsize = AtomicUsize::new(0);
+    static IPS: [AtomicUsize; MAX_FRAMES] = [ZERO; MAX_FRAMES];
+    static NUM_IPS: AtomicUsize = AtomicUsize::new(0);
+    static DONE: AtomicBool = AtomicBool::new(false);
+
+    /// Generation of the last request sent, see [`PENDING`].
+    static GENERATION: AtomicUsize = AtomicUsize::new(0);
+    /// Generation of the request waiting for a reply, `0` if none is. A
+    /// handler claims the request by resetting it, so replies arriving after
+    /// the request timed out are dropped instead of overwriting the buffers
+    /// while the next thread's stack is read.
+    static PENDING: AtomicUsize = AtomicUsize::new(0);
+    /// Number of signals sent and handled, for telling whether any are
+    /// still in flight.
+    static SENT: AtomicUsize = AtomicUsize::new(0);
+    static HANDLED: AtomicUsize = AtomicUsize::new(0);
+
+    /// Only one thread may use the static buffers at a time.
+    static CAPTURE_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Prefix of `siginfo_t` for signals carrying a value, with the layout of
+    /// the kernel's `_rt` union member.
+    #[repr(C)]
+    struct QueuedSigInfo {
+        signo: libc::c_int,
+        errno: libc::c_int,
+        code: libc::c_int,
+        pid: libc::pid_t,
+        uid: libc::uid_t,
+        value: libc::sigval,
+    }
+
+    extern "C" fn on_signal(_: libc::c_int, info: *mut libc::siginfo_t, _: *mut libc::c_void) {
+        // SAFETY: the kernel passes a valid `siginfo_t` with `SA_SIGINFO`,
+        // which is larger than its prefix read here.
+        let generation = unsafe { (*info.cast::<QueuedSigInfo>()).value.sival_ptr as usize };
+        let claimed = generation != 0
+            && PENDING
+                .compare_exchange(generation, 0, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok();
+
+        if claimed {
+            let mut n = 0;
+            // SAFETY: nothing else can be tracing on this thread right now:
+            // it was interrupted by our signal.
+            unsafe {
+                backtrace::trace_unsynchronized(|frame| {
+                    IPS[n].store(frame.ip() as usize, Ordering::Relaxed);
+                    n += 1;
+                    n < MAX_FRAMES
+                });
+            }
+            NUM_IPS.store(n, Ordering::Release);
+            DONE.store(true, Ordering::Release);
+        }
+        HANDLED.fetch_add(1, Ordering::Release);
+    }
+
+    fn thread_ids() -> Vec<i64> {
+        let entries = match std::fs::read_dir("/proc/self/task") {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+
+        entries
+            .filter_map(|x| x.ok()?.file_name().to_str()?.parse().ok())
+            .collect()
+    }
+
+    fn thread_name(tid: i64) -> Option<String> {
+        let comm = std::fs::read_to_string(format!("/proc/self/task/{}/comm", tid)).ok()?;
+        Some(comm.trim_end().to_owned())
+    }
+
+    /// Interrupt the given thread and wait for it to store its stack.
+    fn capture_ips(tid: i64, signal: libc::c_int) -> Option<Vec<usize>> {
+        let generation = GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+        DONE.store(false, Ordering::Release);
+        PENDING.store(generation, Ordering::Release);
+
+        // SAFETY: plain syscalls, any failure is reported via the return
+        // value. The `siginfo_t` is zeroed and large enough for the prefix
+        // written through the cast.
+        let sent = unsafe {
+            let pid = libc::getpid();
+            let mut info: libc::siginfo_t = std::mem::zeroed();
+            let queued = &mut *(&mut info as *mut libc::siginfo_t).cast::<QueuedSigInfo>();
+            queued.signo = signal;
+            queued.code = libc::SI_QUEUE;
+            queued.pid = pid;
+            queued.uid = libc::getuid();
+            queued.value.sival_ptr = generation as *mut libc::c_void;
+            libc::syscall(libc::SYS_rt_tgsigqueueinfo, pid, tid, signal, &info) == 0
+        };
+        if !sent {
+            PENDING.store(0, Ordering::Release);
+            return None;
+        }
+        SENT.fetch_add(1, Ordering::Relaxed);
+
+        let start = Instant::now();
+        while !DONE.load(Ordering::Acquire) {
+            // Withdraw the request, unless a handler already claimed it and
+            // is about to finish writing the stack.
+            if start.elapsed() > TIMEOUT
+                && PENDING
+                    .compare_exchange(generation, 0, Ordering::AcqRel, Ordering::Relaxed)
+                    .is_ok()
+            {
+                return None;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        let n = NUM_IPS.load(Ordering::Acquire);
+        Some(IPS[..n].iter().map(|x| x.load(Ordering::Relaxed)).collect())
+    }
+
+    /// Wait for the signals that are still in flight, so the handler can be
+    /// removed without them hitting the previous disposition.
+    fn all_handled() -> bool {
+        let start = Instant::now();
+        while HANDLED.load(Ordering::Acquire) != SENT.load(Ordering::Relaxed) {
+            if start.elapsed() > TIMEOUT {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        true
+    }
+
+    fn resolve(ips: &[usize], symbolizer: Symbolizer) -> Vec<Frame> {
+        let mut frames = Frame::resolve_ips(ips, symbolizer);
+
+        // Drop our signal handler frames and the signal trampoline after it.
+        let handler = frames.iter().position(|x| {
+            matches!(&x.name, Some(name) if name.contains("color_backtrace::threads::imp::on_signal"))
+        });
+        if let Some(pos) = handler {
+            frames.drain(..(pos + 2).min(frames.len()));
+            for (frame, n) in frames.iter_mut().zip(1..) {
+                frame.n = n;
+            }
+        }
+
+        frames
+    }
+
+    pub(crate) fn capture_other_threads(symbolizer: Symbolizer) -> Vec<ThreadTrace> {
+        let _lock = CAPTURE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let signal = libc::SIGRTMIN();
+
+        // SAFETY: we install a handler for a signal reserved for applications
+        // and restore the previous one afterwards.
+        let mut old_action: libc::sigaction = unsafe { std::mem::zeroed() };
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_signal
+                as extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void)
+                as libc::sighandler_t;
+            action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            if libc::sigaction(signal, &action, &mut old_action) != 0 {
+                return Vec::new();
+            }
+        }
+
+        // SAFETY: plain syscall without preconditions.
+        let current_tid = unsafe { libc::syscall(libc::SYS_gettid) };
+        let traces = thread_ids()
+            .into_iter()
+            .filter(|&tid| tid != current_tid)
+            .filter_map(|tid| {
+                Some(ThreadTrace {
+                    tid,
+                    name: thread_name(tid),
+                    frames: resolve(&capture_ips(tid, signal)?, symbolizer),
+                })
+            })
+            .collect();
+
+        // A signal still pending after giving up on a thread would run the
+        // previous disposition, by default terminating the process. Keep our
+        // handler, which drops such late replies, in that case.
+        if all_handled() {
+            // SAFETY: restores the action saved above.
+            unsafe {
+                libc::sigaction(signal, &old_action, std::ptr::null_mut());
+            }
+        }
+
+        traces
+    }
+}
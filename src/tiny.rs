@@ -0,0 +1,146 @@
+//! Minimal panic handler for size-sensitive binaries.
+//!
+//! Enabled via the `tiny` feature, which works without the default features.
+//! Frames are captured with the `backtrace` crate and rendered compactly by
+//! [`BareFormatter`], without `termcolor`, module resolution or source
+//! snippets.
+//!
+//! ```rust,no_run
+//! color_backtrace::tiny::install();
+//! ```
+
+use crate::bare::{BareFormatter, BareFrame};
+use std::io::{IsTerminal as _, Write as _};
+
+// See the alias of the same name in the printer module.
+#[allow(deprecated)]
+type PanicInfo<'a> = std::panic::PanicInfo<'a>;
+
+/// Symbols of the panic machinery, hidden along with everything called by
+/// them.
+const PANIC_SYM_PREFIXES: &[&str] = &[
+    "std::panicking::",
+    "core::panicking::",
+    "std::panic::",
+    "std::sys::backtrace::__rust_end_short_backtrace",
+    "std::sys_common::backtrace::__rust_end_short_backtrace",
+    "rust_begin_unwind",
+    "__rustc::rust_begin_unwind",
+];
+
+/// Symbols of the runtime startup code, hidden along with their callers.
+const RUNTIME_INIT_SYM_PREFIXES: &[&str] = &[
+    "std::rt::lang_start",
+    "std::sys::backtrace::__rust_begin_short_backtrace",
+];
+
+/// Install a panic handler printing compact, colored (if stderr is a
+/// terminal and `NO_COLOR` isn't set) backtraces.
+pub fn install() {
+    let color = std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    install_with(BareFormatter::new().color(color).print_addresses(false));
+}
+
+/// Install a panic handler printing with the given formatter.
+///
+/// Frames are always printed compactly.
+pub fn install_with(formatter: BareFormatter<'static>) {
+    let formatter = formatter.compact(true);
+    std::panic::set_hook(Box::new(move |pi| {
+        let report = render_panic(&formatter, pi);
+        let _ = std::io::stderr().lock().write_all(report.as_bytes());
+    }));
+}
+
+fn render_panic(formatter: &BareFormatter<'_>, pi: &PanicInfo<'_>) -> String {
+    let trace = backtrace::Backtrace::new();
+    let frames = trace
+        .frames()
+        .iter()
+        .flat_map(|frame| {
+            let ip = frame.ip() as usize;
+            frame.symbols().iter().map(move |sym| (ip, sym))
+        })
+        .map(|(ip, sym)| {
+            let name = sym.name().map(|x| format!("{:#}", x));
+            let file = sym.filename().map(|x| x.display().to_string());
+            (ip, name, file, sym.lineno())
+        })
+        .collect::<Vec<_>>();
+
+    let is_any = |name: &Option<String>, prefixes: &[&str]| {
+        name.as_deref()
+            .is_some_and(|x| prefixes.iter().any(|p| x.starts_with(p)))
+    };
+    // Look for the panic machinery only below the runtime initialization,
+    // which uses `catch_unwind` itself.
+    let end = frames
+        .iter()
+        .position(|x| is_any(&x.1, RUNTIME_INIT_SYM_PREFIXES))
+        .unwrap_or(frames.len());
+    // The panic machinery also shows up further down below `catch_unwind`,
+    // so skip only the first run of it.
+    let start = match frames[..end]
+        .iter()
+        .position(|x| is_any(&x.1, PANIC_SYM_PREFIXES))
+    {
+        Some(first) => {
+            first
+                + frames[first..end]
+                    .iter()
+                    .take_while(|x| is_any(&x.1, PANIC_SYM_PREFIXES))
+                    .count()
+        }
+        None => 0,
+    };
+
+    let frames: Vec<_> = frames[start..end]
+        .iter()
+        .map(|(ip, name, file, line)| BareFrame {
+            name: name.as_deref(),
+            file: file.as_deref(),
+            line: *line,
+            ip: *ip,
+        })
+        .collect();
+
+    let payload = pi.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("<non string panic payload>");
+
+    let mut out = String::new();
+    let _ = formatter.write_panic(&mut out, &message, pi.location(), &frames);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[inline(never)]
+    fn broken() {
+        panic!("broken");
+    }
+
+    #[test]
+    fn caught_panic() {
+        static REPORT: Mutex<String> = Mutex::new(String::new());
+
+        let formatter = BareFormatter::new().compact(true);
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |pi| {
+            *REPORT.lock().unwrap() = render_panic(&formatter, pi);
+        }));
+        let result = std::panic::catch_unwind(broken);
+        std::panic::set_hook(previous);
+
+        assert!(result.is_err());
+        let report = REPORT.lock().unwrap();
+        assert!(report.contains("tiny::tests::broken"), "{}", report);
+        assert!(!report.contains("render_panic"), "{}", report);
+    }
+}
@@ -0,0 +1,89 @@
+//! Unwrapping with pretty panics, see [`PrettyUnwrap`].
+
+use std::fmt::Debug;
+
+/// Extension trait for unwrapping `Result`s and `Option`s with a pretty panic.
+///
+/// Works just like `unwrap` and `expect`, but the panic is reported nicely
+/// even if no `color_backtrace` panic handler was installed. Handy for
+/// examples and quick scripts that want nice output without setting up
+/// anything.
+///
+/// Without a `color_backtrace` handler, the report is printed to stderr by
+/// the default printer and the panic unwinds without invoking the panic hook,
+/// which is left as it is.
+///
+/// # Example
+///
+/// ```rust
+/// use color_backtrace::PrettyUnwrap;
+///
+/// let config = std::fs::read_to_string("Cargo.toml").pretty_expect("can't read config");
+/// let first = config.lines().next().pretty_unwrap();
+/// ```
+pub trait PrettyUnwrap<T> {
+    /// Return the contained value, panicking with a pretty report otherwise.
+    fn pretty_unwrap(self) -> T;
+
+    /// Return the contained value, panicking with a pretty report including
+    /// the given message otherwise.
+    fn pretty_expect(self, msg: &str) -> T;
+}
+
+impl<T, E: Debug> PrettyUnwrap<T> for Result<T, E> {
+    #[track_caller]
+    fn pretty_unwrap(self) -> T {
+        match self {
+            Ok(x) => x,
+            Err(e) => failed(format_args!(
+                "called `Result::pretty_unwrap()` on an `Err` value: {:?}",
+                e
+            )),
+        }
+    }
+
+    #[track_caller]
+    fn pretty_expect(self, msg: &str) -> T {
+        match self {
+            Ok(x) => x,
+            Err(e) => failed(format_args!("{}: {:?}", msg, e)),
+        }
+    }
+}
+
+impl<T> PrettyUnwrap<T> for Option<T> {
+    #[track_caller]
+    fn pretty_unwrap(self) -> T {
+        match self {
+            Some(x) => x,
+            None => failed(format_args!(
+                "called `Option::pretty_unwrap()` on a `None` value"
+            )),
+        }
+    }
+
+    #[track_caller]
+    fn pretty_expect(self, msg: &str) -> T {
+        match self {
+            Some(x) => x,
+            None => failed(format_args!("{}", msg)),
+        }
+    }
+}
+
+/// Panic with the given message, making sure it's printed nicely.
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn failed(msg: std::fmt::Arguments<'_>) -> ! {
+    if crate::printer::is_installed() {
+        panic!("{}", msg)
+    }
+    let msg = msg.to_string();
+    crate::printer::print_unhooked_panic(
+        &msg,
+        std::panic::Location::caller(),
+        "color_backtrace::unwrap::failed",
+    );
+    std::panic::resume_unwind(Box::new(msg))
+}
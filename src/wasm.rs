@@ -0,0 +1,171 @@
+//! Support for `wasm32-unknown-unknown`, where there's neither a stderr nor
+//! native stack unwinding.
+//!
+//! Panics are printed to the browser console via `console.error`, with colors
+//! translated to CSS, and the stack is taken from a JavaScript `Error`.
+//!
+//! ```rust,ignore
+//! #[wasm_bindgen(start)]
+//! pub fn start() {
+//!     color_backtrace::wasm::set_once();
+//! }
+//! ```
+
+use crate::{BacktracePrinter, Frame};
+use std::io;
+use std::sync::Once;
+use termcolor::{Color, ColorSpec, WriteColor};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsValue;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console, js_name = error, variadic)]
+    fn console_error(format: &str, styles: Box<[JsValue]>);
+
+    #[wasm_bindgen(js_name = Error)]
+    type JsError;
+
+    #[wasm_bindgen(constructor, js_class = "Error")]
+    fn new() -> JsError;
+
+    #[wasm_bindgen(method, getter, structural)]
+    fn stack(this: &JsError) -> String;
+}
+
+/// Install a default `BacktracePrinter` writing to the browser console.
+///
+/// Calling this multiple times is fine, only the first call has an effect.
+pub fn set_once() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| BacktracePrinter::default().install(ConsoleStream::default()));
+}
+
+/// Output stream writing to the browser console via `console.error`.
+///
+/// Output is buffered and emitted as a single console message on flush, using
+/// `%c` directives to apply the colors.
+#[derive(Debug, Default)]
+pub struct ConsoleStream {
+    text: String,
+    styles: Vec<String>,
+}
+
+impl ConsoleStream {
+    fn push_style(&mut self, css: String) {
+        self.text.push_str("%c");
+        self.styles.push(css);
+    }
+}
+
+impl io::Write for ConsoleStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Escape `%` to keep it from being interpreted as format directive.
+        self.text
+            .push_str(&String::from_utf8_lossy(buf).replace('%', "%%"));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.text.is_empty() {
+            let styles = self.styles.drain(..).map(JsValue::from).collect();
+            console_error(&std::mem::take(&mut self.text), styles);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ConsoleStream {
+    fn drop(&mut self) {
+        let _ = io::Write::flush(self);
+    }
+}
+
+impl WriteColor for ConsoleStream {
+    fn supports_color(&self) -> bool {
+        true
+    }
+
+    fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
+        let mut css = String::new();
+        if let Some(fg) = spec.fg() {
+            css.push_str(&format!("color: {};", css_color(fg, spec.intense())));
+        }
+        if let Some(bg) = spec.bg() {
+            css.push_str(&format!(
+                "background-color: {};",
+                css_color(bg, spec.intense())
+            ));
+        }
+        if spec.bold() {
+            css.push_str("font-weight: bold;");
+        }
+        if spec.dimmed() {
+            css.push_str("opacity: 0.6;");
+        }
+        self.push_style(css);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        self.push_style(String::new());
+        Ok(())
+    }
+}
+
+fn css_color(color: &Color, intense: bool) -> String {
+    let name = match (color, intense) {
+        (Color::Black, false) => "black",
+        (Color::Black, true) => "gray",
+        (Color::Red, false) => "firebrick",
+        (Color::Red, true) => "red",
+        (Color::Green, false) => "green",
+        (Color::Green, true) => "limegreen",
+        (Color::Yellow, false) => "goldenrod",
+        (Color::Yellow, true) => "gold",
+        (Color::Blue, false) => "royalblue",
+        (Color::Blue, true) => "dodgerblue",
+        (Color::Magenta, false) => "darkmagenta",
+        (Color::Magenta, true) => "magenta",
+        (Color::Cyan, false) => "darkcyan",
+        (Color::Cyan, true) => "cyan",
+        (Color::White, false) => "lightgray",
+        (Color::White, true) => "white",
+        (Color::Rgb(r, g, b), _) => return format!("rgb({}, {}, {})", r, g, b),
+        _ => "inherit",
+    };
+    name.to_owned()
+}
+
+/// Capture the current stack from a JavaScript `Error`.
+///
+/// Stack lines look like `at my_crate::main::h0123 (wasm://...:0x1f2)` in
+/// Chromium and `my_crate::main::h0123@wasm://...:0x1f2` in Firefox.
+pub(crate) fn capture_frames() -> Vec<Frame> {
+    let stack = JsError::new().stack();
+    let mut frames = Vec::new();
+    for line in stack.lines() {
+        let line = line.trim();
+        let (name, location) = if let Some(rest) = line.strip_prefix("at ") {
+            match rest.strip_suffix(')').and_then(|x| x.rsplit_once(" (")) {
+                Some((name, location)) => (Some(name), Some(location)),
+                None => (None, Some(rest)),
+            }
+        } else if let Some((name, location)) = line.split_once('@') {
+            (Some(name).filter(|x| !x.is_empty()), Some(location))
+        } else {
+            continue;
+        };
+
+        frames.push(Frame {
+            n: frames.len() + 1,
+            name: name.map(|x| rustc_demangle::demangle(x).to_string()),
+            lineno: None,
+            colno: None,
+            filename: location.map(Into::into),
+            ip: 0,
+            symbol_address: None,
+        });
+    }
+    frames
+}
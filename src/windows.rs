@@ -0,0 +1,189 @@
+//! Output streams for Windows applications without a console.
+//!
+//! GUI applications typically have no stderr attached, so panics printed the
+//! usual way simply vanish. [`DebugOutputStream`] sends them to the debugger
+//! via `OutputDebugString` (visible in Visual Studio or DebugView), while
+//! [`EventLogStream`] files them in the Windows Event Log.
+//!
+//! ```rust,ignore
+//! use color_backtrace::{windows::DebugOutputStream, BacktracePrinter};
+//! BacktracePrinter::new().install(DebugOutputStream);
+//! ```
+
+use std::ffi::{c_void, OsStr};
+use std::io;
+use std::os::windows::ffi::OsStrExt;
+use termcolor::{ColorSpec, WriteColor};
+
+type Handle = *mut c_void;
+
+const EVENTLOG_ERROR_TYPE: u16 = 0x0001;
+
+/// Maximum length of a single event log string, in UTF-16 code units.
+const MAX_EVENT_STRING: usize = 31839;
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn OutputDebugStringW(output_string: *const u16);
+}
+
+#[link(name = "advapi32")]
+extern "system" {
+    fn RegisterEventSourceW(unc_server_name: *const u16, source_name: *const u16) -> Handle;
+    fn DeregisterEventSource(event_log: Handle) -> i32;
+    fn ReportEventW(
+        event_log: Handle,
+        ty: u16,
+        category: u16,
+        event_id: u32,
+        user_sid: *mut c_void,
+        num_strings: u16,
+        data_size: u32,
+        strings: *const *const u16,
+        raw_data: *mut c_void,
+    ) -> i32;
+}
+
+/// Encode a string as NUL-terminated UTF-16.
+fn to_wide(s: &str) -> Vec<u16> {
+    OsStr::new(s).encode_wide().chain(Some(0)).collect()
+}
+
+/// Implement `WriteColor` for a plain-text stream.
+macro_rules! impl_plain_write_color {
+    ($ty:ty) => {
+        impl WriteColor for $ty {
+            fn supports_color(&self) -> bool {
+                false
+            }
+
+            fn set_color(&mut self, _: &ColorSpec) -> io::Result<()> {
+                Ok(())
+            }
+
+            fn reset(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+    };
+}
+
+// ============================================================================================== //
+// [OutputDebugString]                                                                            //
+// ============================================================================================== //
+
+/// Output stream writing to the attached debugger via `OutputDebugString`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DebugOutputStream;
+
+impl io::Write for DebugOutputStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let wide = to_wide(&String::from_utf8_lossy(buf));
+        // SAFETY: `wide` is a valid, NUL-terminated UTF-16 string.
+        unsafe { OutputDebugStringW(wide.as_ptr()) };
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl_plain_write_color!(DebugOutputStream);
+
+// ============================================================================================== //
+// [Event Log]                                                                                    //
+// ============================================================================================== //
+
+/// Output stream filing every panic as an error in the Windows Event Log.
+///
+/// Written data is buffered and reported as a single event on flush, which the
+/// panic handler does after every panic.
+#[derive(Debug)]
+pub struct EventLogStream {
+    // Stored as integer to keep the stream `Send` and `Sync`.
+    handle: usize,
+    event_id: u32,
+    buf: Vec<u8>,
+}
+
+impl EventLogStream {
+    /// Register the given event source with the local Event Log.
+    ///
+    /// Without a matching registry entry for the source, the Event Viewer
+    /// shows a generic notice in front of the message, but still displays it.
+    pub fn new(source: &str) -> io::Result<Self> {
+        let source = to_wide(source);
+        // SAFETY: `source` is a valid, NUL-terminated UTF-16 string.
+        let handle = unsafe { RegisterEventSourceW(std::ptr::null(), source.as_ptr()) };
+        if handle.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            handle: handle as usize,
+            event_id: 1,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Controls the event ID of the reported events.
+    ///
+    /// Defaults to `1`.
+    pub fn event_id(mut self, id: u32) -> Self {
+        self.event_id = id;
+        self
+    }
+}
+
+impl io::Write for EventLogStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+
+        let text = String::from_utf8_lossy(&std::mem::take(&mut self.buf)).into_owned();
+        let mut wide = to_wide(&text);
+        if wide.len() > MAX_EVENT_STRING {
+            wide.truncate(MAX_EVENT_STRING - 1);
+            wide.push(0);
+        }
+        let strings = [wide.as_ptr()];
+
+        // SAFETY: the handle is valid until drop and `strings` holds a
+        // single NUL-terminated UTF-16 string.
+        let ok = unsafe {
+            ReportEventW(
+                self.handle as Handle,
+                EVENTLOG_ERROR_TYPE,
+                0,
+                self.event_id,
+                std::ptr::null_mut(),
+                1,
+                0,
+                strings.as_ptr(),
+                std::ptr::null_mut(),
+            )
+        };
+
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for EventLogStream {
+    fn drop(&mut self) {
+        let _ = io::Write::flush(self);
+        // SAFETY: the handle was obtained from `RegisterEventSourceW`.
+        unsafe { DeregisterEventSource(self.handle as Handle) };
+    }
+}
+
+impl_plain_write_color!(EventLogStream);
@@ -0,0 +1,328 @@
+//! Passing panics between processes.
+//!
+//! Short-lived workers can't always print their panics in a useful way, e.g.
+//! when their stderr isn't a terminal or gets interleaved. Instead, they can
+//! send the panic over a pipe or socket to a supervisor, which renders it
+//! with its own printer, colors and frame filters via
+//! [`BacktracePrinter::print_report`].
+//!
+//! In the worker:
+//!
+//! ```rust,no_run
+//! # #[cfg(unix)] {
+//! use std::{fs::File, os::fd::FromRawFd};
+//!
+//! // Safety: fd 3 is the pipe to the supervisor.
+//! color_backtrace::wire::install(unsafe { File::from_raw_fd(3) });
+//! # }
+//! ```
+//!
+//! In the supervisor:
+//!
+//! ```rust,no_run
+//! use color_backtrace::{default_output_stream, wire, BacktracePrinter};
+//! use std::io::BufReader;
+//! # let pipe = std::io::empty();
+//!
+//! let printer = BacktracePrinter::new();
+//! let mut input = BufReader::new(pipe);
+//! while let Some(report) = wire::read_report(&mut input)? {
+//!     printer.print_report(&report, &mut default_output_stream())?;
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! The format is line based text. Each report starts with a version line and
+//! ends with `end`, so reports can be streamed one after another:
+//!
+//! ```text
+//! color-backtrace-report 1
+//! message→oh no
+//! location→src/main.rs→4→5
+//! thread→main
+//! fingerprint→3ca94de04925689e
+//! hidden→0
+//! frame→1→55f1c2a4e0b3→→my_app::main→/src/main.rs→4→5
+//! end
+//! ```
+//!
+//! Fields are separated by tabs, shown as `→` above, with tabs, line breaks
+//! and backslashes in values escaped. Absent values are empty.
+//!
+//! To use another format, e.g. JSON, enable the `serde` feature, which
+//! implements `Serialize` and `Deserialize` for [`PanicReport`].
+
+use crate::{fingerprint, BacktracePrinter, Frame, PanicHook, PanicLocation, PanicReport};
+use std::io::{self, BufRead, Write};
+use std::sync::Mutex;
+
+/// First line of every report.
+const HEADER: &str = "color-backtrace-report 1";
+
+/// Install a panic handler sending the panics to `out` instead of printing
+/// them.
+pub fn install(out: impl Write + Send + 'static) {
+    std::panic::set_hook(panic_hook(out))
+}
+
+/// Create a panic handler sending the panics to `out`, see [`install`].
+///
+/// All frames are sent, filtering is left to the receiving side.
+pub fn panic_hook(out: impl Write + Send + 'static) -> Box<PanicHook> {
+    let out = Mutex::new(out);
+    Box::new(move |pi| {
+        let frames = BacktracePrinter::default().capture_frames();
+        let report = PanicReport {
+            message: crate::printer::payload_as_str(pi.payload()).into_owned(),
+            location: pi.location().map(|x| PanicLocation {
+                file: x.file().to_owned(),
+                line: x.line(),
+                column: x.column(),
+            }),
+            thread: std::thread::current().name().map(ToOwned::to_owned),
+            hidden_frames: 0,
+            fingerprint: fingerprint(&frames),
+            frames,
+        };
+        let mut out = out.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = write_report(&report, &mut *out).and_then(|()| out.flush()) {
+            eprintln!("Error while sending panic report: {:?}", e);
+        }
+    })
+}
+
+/// Serialize a report to `out`.
+pub fn write_report(report: &PanicReport, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "{}", HEADER)?;
+    writeln!(out, "message\t{}", escape(&report.message))?;
+    if let Some(location) = &report.location {
+        writeln!(
+            out,
+            "location\t{}\t{}\t{}",
+            escape(&location.file),
+            location.line,
+            location.column
+        )?;
+    }
+    if let Some(thread) = &report.thread {
+        writeln!(out, "thread\t{}", escape(thread))?;
+    }
+    writeln!(out, "fingerprint\t{:016x}", report.fingerprint)?;
+    writeln!(out, "hidden\t{}", report.hidden_frames)?;
+    for frame in &report.frames {
+        let optional = |x: Option<String>| x.unwrap_or_default();
+        writeln!(
+            out,
+            "frame\t{}\t{:x}\t{}\t{}\t{}\t{}\t{}",
+            frame.n,
+            frame.ip,
+            optional(frame.symbol_address.map(|x| format!("{:x}", x))),
+            escape(frame.name.as_deref().unwrap_or("")),
+            escape(&optional(
+                frame.filename.as_ref().map(|x| x.to_string_lossy().into())
+            )),
+            optional(frame.lineno.map(|x| x.to_string())),
+            optional(frame.colno.map(|x| x.to_string())),
+        )?;
+    }
+    writeln!(out, "end")
+}
+
+/// Read the next report from `input`, or `None` at the end of the input.
+///
+/// Lines before the start of a report are skipped, so the reports can share
+/// a channel with other output. Malformed reports fail with
+/// `io::ErrorKind::InvalidData`.
+pub fn read_report(input: &mut impl BufRead) -> io::Result<Option<PanicReport>> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if line.trim_end() == HEADER {
+            break;
+        }
+    }
+
+    let mut report = PanicReport {
+        message: String::new(),
+        location: None,
+        thread: None,
+        frames: Vec::new(),
+        hidden_frames: 0,
+        fingerprint: 0,
+    };
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Err(invalid("unexpected end of report"));
+        }
+        let mut fields = line.trim_end_matches(['\n', '\r']).split('\t');
+        let mut next = || fields.next().map(unescape).unwrap_or_default();
+        match next().as_str() {
+            "end" => return Ok(Some(report)),
+            "message" => report.message = next(),
+            "location" => {
+                report.location = Some(PanicLocation {
+                    file: next(),
+                    line: parse(&next())?,
+                    column: parse(&next())?,
+                })
+            }
+            "thread" => report.thread = Some(next()),
+            "fingerprint" => {
+                report.fingerprint =
+                    u64::from_str_radix(&next(), 16).map_err(|_| invalid("invalid fingerprint"))?
+            }
+            "hidden" => report.hidden_frames = parse(&next())?,
+            "frame" => {
+                let n = parse(&next())?;
+                let ip = usize::from_str_radix(&next(), 16).map_err(|_| invalid("invalid ip"))?;
+                let symbol_address = Some(next())
+                    .filter(|x| !x.is_empty())
+                    .map(|x| usize::from_str_radix(&x, 16))
+                    .transpose()
+                    .map_err(|_| invalid("invalid symbol address"))?;
+                let name = Some(next()).filter(|x| !x.is_empty());
+                let filename = Some(next()).filter(|x| !x.is_empty()).map(Into::into);
+                let lineno = parse_optional(&next())?;
+                let colno = parse_optional(&next())?;
+                report.frames.push(Frame {
+                    n,
+                    name,
+                    lineno,
+                    colno,
+                    filename,
+                    ip,
+                    symbol_address,
+                });
+            }
+            // Added in later versions, skip.
+            _ => {}
+        }
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn parse<T: std::str::FromStr>(text: &str) -> io::Result<T> {
+    text.parse()
+        .map_err(|_| invalid(&format!("invalid number `{}`", text)))
+}
+
+fn parse_optional<T: std::str::FromStr>(text: &str) -> io::Result<Option<T>> {
+    match text {
+        "" => Ok(None),
+        text => parse(text).map(Some),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(report: &PanicReport) -> PanicReport {
+        let mut buf = Vec::new();
+        write_report(report, &mut buf).unwrap();
+        let mut input = &buf[..];
+        let parsed = read_report(&mut input).unwrap().unwrap();
+        assert!(read_report(&mut input).unwrap().is_none());
+        parsed
+    }
+
+    #[test]
+    fn escaping() {
+        let report = PanicReport {
+            message: "tab\there\nnew line\r\n\\n is not a line break\\".into(),
+            location: Some(PanicLocation {
+                file: "C:\\src\\main.rs".into(),
+                line: 4,
+                column: 5,
+            }),
+            thread: Some("worker\t1".into()),
+            frames: vec![Frame {
+                n: 1,
+                name: Some("my_app::main::{{closure}}".into()),
+                lineno: Some(4),
+                colno: Some(5),
+                filename: Some("/src/with\ttab/main.rs".into()),
+                ip: 0x55f1_c2a4_e0b3,
+                symbol_address: Some(0x55f1_c2a4_e000),
+            }],
+            hidden_frames: 3,
+            fingerprint: 0x3ca9_4de0_4925_689e,
+        };
+        assert_eq!(
+            format!("{:?}", round_trip(&report)),
+            format!("{:?}", report)
+        );
+    }
+
+    #[test]
+    fn absent_fields() {
+        let report = PanicReport {
+            message: String::new(),
+            location: None,
+            thread: None,
+            frames: vec![Frame {
+                n: 1,
+                name: None,
+                lineno: None,
+                colno: None,
+                filename: None,
+                ip: 0,
+                symbol_address: None,
+            }],
+            hidden_frames: 0,
+            fingerprint: 0,
+        };
+        assert_eq!(
+            format!("{:?}", round_trip(&report)),
+            format!("{:?}", report)
+        );
+    }
+
+    #[test]
+    fn skips_unrelated_lines() {
+        let text = "noise\ncolor-backtrace-report 1\nmessage\toh no\nfuture\tfield\nend\n";
+        let report = read_report(&mut text.as_bytes()).unwrap().unwrap();
+        assert_eq!(report.message, "oh no");
+        assert!(read_report(&mut "color-backtrace-report 1\n".as_bytes()).is_err());
+    }
+}